@@ -69,7 +69,7 @@ async fn main() -> Result<()> {
     };
     let initial_balance = initial_balance_str
         .trim()
-        .parse::<u32>()
+        .parse::<Csh>()
         .unwrap_or_default();
     io::stdout().flush().unwrap();
 
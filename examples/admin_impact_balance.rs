@@ -64,10 +64,20 @@ async fn main() -> Result<()> {
         Err(error) => panic!("{}", error),
     };
 
+    let username = match Username::new(&name) {
+        Ok(username) => username,
+        Err(error) => panic!("{}", error),
+    };
+
     let mut session = CCashSession::new(&instance_url);
     session.establish_connection().await.expect("{}");
-    match methods::admin::impact_balance(&session, &admin_user, &name, balance_modifier)
-        .await
+    match methods::admin::impact_balance(
+        &session,
+        &admin_user,
+        &username,
+        balance_modifier,
+    )
+    .await
     {
         Ok(_) => println!("Impacted balance for {name} by {balance_modifier}"),
         Err(e) =>
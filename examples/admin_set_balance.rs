@@ -52,7 +52,7 @@ async fn main() -> Result<()> {
         Ok(v) => v,
         Err(e) => panic!("{}", e),
     };
-    let new_balance = new_balance_str.trim().to_string().parse::<u32>().unwrap();
+    let new_balance = new_balance_str.trim().to_string().parse::<Csh>().unwrap();
     io::stdout().flush().unwrap();
 
     let admin_user = match CCashUser::new(&admin_name, &admin_password) {
@@ -60,9 +60,15 @@ async fn main() -> Result<()> {
         Err(error) => panic!("{}", error),
     };
 
+    let username = match Username::new(&name) {
+        Ok(username) => username,
+        Err(error) => panic!("{}", error),
+    };
+
     let mut session = CCashSession::new(&instance_url);
     session.establish_connection().await.expect("{}");
-    match methods::admin::set_balance(&session, &admin_user, &name, new_balance).await {
+    match methods::admin::set_balance(&session, &admin_user, &username, new_balance).await
+    {
         Ok(_) => println!("Set balance for {name} to {new_balance}"),
         Err(e) => println!("Could not change balance to {new_balance} for {name}: {e}"),
     }
@@ -0,0 +1,39 @@
+#![allow(unused_assignments)]
+
+use ccash_rs::{blocking::BlockingCCashSession, *};
+use std::io::{self, prelude::*};
+
+fn main() -> Result<()> {
+    print!("Please enter the instance URL > ");
+    io::stdout().flush().unwrap();
+    let mut instance_url = String::new();
+    match io::stdin().read_line(&mut instance_url) {
+        Ok(v) => v,
+        Err(e) => panic!("{}", e),
+    };
+    instance_url = instance_url.trim().to_string();
+    io::stdout().flush().unwrap();
+
+    print!("Please enter your username > ");
+    io::stdout().flush().unwrap();
+    let mut name = String::new();
+    match io::stdin().read_line(&mut name) {
+        Ok(v) => v,
+        Err(e) => panic!("{}", e),
+    };
+    name = name.trim().to_string();
+    io::stdout().flush().unwrap();
+
+    let user = match CCashUser::new(&name, "") {
+        Ok(user) => user,
+        Err(error) => panic!("{}", error),
+    };
+
+    let mut session = BlockingCCashSession::new(&instance_url);
+    session.establish_connection().expect("{}");
+    println!(
+        "Balance: {}",
+        blocking::methods::get_balance(&session, &user).expect("{}")
+    );
+    Ok(())
+}
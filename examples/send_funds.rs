@@ -52,7 +52,7 @@ async fn main() -> Result<()> {
         Ok(v) => v,
         Err(e) => panic!("{}", e),
     };
-    let amount = match amount_string.trim().parse::<u32>() {
+    let amount = match amount_string.trim().parse::<Csh>() {
         Ok(amt) => amt,
         Err(e) => panic!("{}", e),
     };
@@ -65,7 +65,7 @@ async fn main() -> Result<()> {
     let mut session = CCashSession::new(&instance_url);
     session.establish_connection().await.expect("{}");
     println!(
-        "Sent {} to {}. {} now has {} CSH",
+        "Sent {} to {}. {} now has {}",
         amount,
         &recipient_name,
         user.get_username(),
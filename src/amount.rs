@@ -0,0 +1,596 @@
+//! This module contains [`Csh`], the type used for amounts of CSH throughout
+//! the crate, and [`CshFormat`], which describes how amounts are displayed and
+//! parsed.
+//!
+//! Balances in `CCash` are unsigned 32-bit integers, so a `Csh` can never be
+//! negative and arithmetic on it is checked. Changes to a balance, such as the
+//! amount given to [`impact_balance`](crate::methods::admin::impact_balance),
+//! are signed deltas in `i64`, which every `Csh` fits into.
+//!
+//! ```
+//! use ccash_rs::{Csh, CshFormat};
+//!
+//! let price: Csh = "1.2k".parse().unwrap();
+//! let balance = Csh::new(5_000);
+//!
+//! assert_eq!(balance.checked_sub(price), Some(Csh::new(3_800)));
+//! assert_eq!(price.checked_sub(balance), None);
+//! assert_eq!(balance.delta_to(price), -3_800);
+//!
+//! assert_eq!(price.to_string(), "1,200 CSH");
+//! assert_eq!(CshFormat::for_locale("de-DE").format(price), "1.200 CSH");
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr, sync::RwLock};
+use thiserror::Error;
+
+/// The format used by the `Display` implementation of [`Csh`], if one has been
+/// set with [`CshFormat::set_default`].
+static DEFAULT_FORMAT: RwLock<Option<CshFormat>> = RwLock::new(None);
+
+/// Enum to describe the errors that could occur when parsing or converting an
+/// amount of CSH.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CshError {
+    /// Error for if the amount to parse is empty.
+    #[error("CshError: No amount was given")]
+    Empty,
+    /// Error for if the amount to parse is not a number.
+    #[error("CshError: {0:?} is not a valid amount")]
+    Invalid(String),
+    /// Error for if the amount is negative.
+    #[error("CshError: Amount cannot be negative")]
+    Negative,
+    /// Error for if the amount is not a whole number of CSH.
+    #[error("CshError: {0:?} is not a whole amount")]
+    Fractional(String),
+    /// Error for if the amount is larger than the largest balance `CCash` can
+    /// hold.
+    #[error("CshError: Amount is larger than {}", Csh::MAX.get())]
+    OutOfRange,
+}
+
+/// An amount of CSH, the currency of `CCash`.
+///
+/// Arithmetic on a `Csh` is checked, as `CCash` rejects balances that are
+/// negative or larger than [`Csh::MAX`]. A `Csh` can be parsed from input such
+/// as `"1,250"` or `"1.2k"` (see [`CshFormat::parse`]), and is displayed with
+/// the default [`CshFormat`].
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(transparent)]
+pub struct Csh(u32);
+
+impl Csh {
+    /// The largest amount of CSH that a `CCash` balance can hold.
+    pub const MAX: Csh = Csh(u32::MAX);
+    /// No CSH.
+    pub const ZERO: Csh = Csh(0);
+
+    /// Creates a new `Csh` of `amount`.
+    #[must_use]
+    pub const fn new(amount: u32) -> Csh { Csh(amount) }
+
+    /// Returns the amount as a `u32`.
+    #[must_use]
+    pub const fn get(self) -> u32 { self.0 }
+
+    /// Returns whether the amount is zero.
+    #[must_use]
+    pub const fn is_zero(self) -> bool { self.0 == 0 }
+
+    /// Adds `rhs` to the amount, returning `None` if the result would be larger
+    /// than [`Csh::MAX`].
+    #[must_use]
+    pub fn checked_add(self, rhs: Csh) -> Option<Csh> {
+        self.0.checked_add(rhs.0).map(Csh)
+    }
+
+    /// Subtracts `rhs` from the amount, returning `None` if the result would be
+    /// negative.
+    #[must_use]
+    pub fn checked_sub(self, rhs: Csh) -> Option<Csh> {
+        self.0.checked_sub(rhs.0).map(Csh)
+    }
+
+    /// Multiplies the amount by `rhs`, returning `None` if the result would be
+    /// larger than [`Csh::MAX`].
+    #[must_use]
+    pub fn checked_mul(self, rhs: u32) -> Option<Csh> { self.0.checked_mul(rhs).map(Csh) }
+
+    /// Applies the signed `delta` to the amount, returning `None` if the result
+    /// would be negative or larger than [`Csh::MAX`].
+    #[must_use]
+    pub fn checked_add_signed(self, delta: i64) -> Option<Csh> {
+        self.to_delta()
+            .checked_add(delta)
+            .and_then(|amount| Csh::try_from(amount).ok())
+    }
+
+    /// Adds `rhs` to the amount, stopping at [`Csh::MAX`].
+    #[must_use]
+    pub fn saturating_add(self, rhs: Csh) -> Csh { Csh(self.0.saturating_add(rhs.0)) }
+
+    /// Subtracts `rhs` from the amount, stopping at [`Csh::ZERO`].
+    #[must_use]
+    pub fn saturating_sub(self, rhs: Csh) -> Csh { Csh(self.0.saturating_sub(rhs.0)) }
+
+    /// Returns the amount as a positive signed delta, such as the amount given
+    /// to [`impact_balance`](crate::methods::admin::impact_balance).
+    #[must_use]
+    pub fn to_delta(self) -> i64 { i64::from(self.0) }
+
+    /// Returns the amount as a negative signed delta, which takes the amount
+    /// away from a balance.
+    #[must_use]
+    pub fn to_negative_delta(self) -> i64 { -i64::from(self.0) }
+
+    /// Returns the signed delta that takes a balance of this amount to a
+    /// balance of `other`.
+    #[must_use]
+    pub fn delta_to(self, other: Csh) -> i64 { other.to_delta() - self.to_delta() }
+
+    /// Formats the amount with the given `format`, rather than the default
+    /// format.
+    #[must_use]
+    pub fn format_with(self, format: &CshFormat) -> String { format.format(self) }
+}
+
+impl From<u32> for Csh {
+    fn from(amount: u32) -> Self { Csh(amount) }
+}
+
+impl From<Csh> for u32 {
+    fn from(amount: Csh) -> Self { amount.0 }
+}
+
+impl From<Csh> for u64 {
+    fn from(amount: Csh) -> Self { u64::from(amount.0) }
+}
+
+impl From<Csh> for i64 {
+    fn from(amount: Csh) -> Self { amount.to_delta() }
+}
+
+impl TryFrom<i64> for Csh {
+    type Error = CshError;
+
+    fn try_from(amount: i64) -> Result<Self, Self::Error> {
+        if amount < 0 {
+            return Err(CshError::Negative);
+        }
+
+        u32::try_from(amount)
+            .map(Csh)
+            .map_err(|_| CshError::OutOfRange)
+    }
+}
+
+impl TryFrom<u64> for Csh {
+    type Error = CshError;
+
+    fn try_from(amount: u64) -> Result<Self, Self::Error> {
+        u32::try_from(amount)
+            .map(Csh)
+            .map_err(|_| CshError::OutOfRange)
+    }
+}
+
+impl PartialEq<u32> for Csh {
+    fn eq(&self, other: &u32) -> bool { self.0 == *other }
+}
+
+impl FromStr for Csh {
+    type Err = CshError;
+
+    /// Parses an amount with [`CshFormat::default`], regardless of the format
+    /// set with [`CshFormat::set_default`], so that `1.5k` always means 1,500.
+    /// See [`CshFormat::parse`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> { CshFormat::default().parse(s) }
+}
+
+impl fmt::Display for Csh {
+    /// Formats the amount with the default [`CshFormat`]. The alternate flag
+    /// (`{:#}`) leaves out the currency symbol.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = CshFormat::get_default();
+        if f.alternate() {
+            f.pad(&format.format_number(*self))
+        } else {
+            f.pad(&format.format(*self))
+        }
+    }
+}
+
+/// Describes how a [`Csh`] is displayed and parsed: the currency symbol, where
+/// it goes, and the character that separates groups of thousands.
+///
+/// The default format displays amounts like `1,250 CSH`. The default can be
+/// changed for the whole program with [`CshFormat::set_default`], which also
+/// changes how transaction logs are displayed, but not how amounts are parsed
+/// with [`FromStr`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CshFormat {
+    symbol: String,
+    symbol_before: bool,
+    thousands_separator: Option<char>,
+}
+
+impl Default for CshFormat {
+    fn default() -> Self {
+        Self {
+            symbol: "CSH".into(),
+            symbol_before: false,
+            thousands_separator: Some(','),
+        }
+    }
+}
+
+impl CshFormat {
+    /// Constructs the default `CshFormat`.
+    #[must_use]
+    pub fn new() -> CshFormat { CshFormat::default() }
+
+    /// Constructs a `CshFormat` that separates thousands the way the locale
+    /// with the given BCP 47 language tag (such as `en-GB` or `de`) does.
+    /// Unknown locales use the default format.
+    #[must_use]
+    pub fn for_locale(locale: &str) -> CshFormat {
+        let locale = locale.replace('_', "-").to_lowercase();
+        let language = locale.split('-').next().unwrap_or_default();
+
+        let separator = match (language, locale.as_str()) {
+            (_, "de-ch" | "it-ch" | "fr-ch" | "rm-ch") => '\'',
+            ("de" | "nl" | "it" | "es" | "pt" | "da" | "id" | "tr" | "el" | "ro", _) =>
+                '.',
+            (
+                "fr" | "sv" | "nb" | "no" | "fi" | "pl" | "cs" | "sk" | "ru" | "uk"
+                | "hu" | "bg" | "lt" | "lv" | "et",
+                _,
+            ) => '\u{a0}',
+            _ => ',',
+        };
+
+        CshFormat::default().thousands_separator(Some(separator))
+    }
+
+    /// Sets the currency symbol. An empty symbol displays amounts without one.
+    #[must_use]
+    pub fn symbol(mut self, symbol: &str) -> CshFormat {
+        self.symbol = symbol.into();
+        self
+    }
+
+    /// Sets whether the currency symbol goes before the amount, as in `$1,250`,
+    /// rather than after it, as in `1,250 CSH`. A symbol placed before the
+    /// amount is not followed by a space.
+    #[must_use]
+    pub fn symbol_before(mut self, symbol_before: bool) -> CshFormat {
+        self.symbol_before = symbol_before;
+        self
+    }
+
+    /// Sets the character that separates groups of thousands, or `None` to not
+    /// separate them.
+    #[must_use]
+    pub fn thousands_separator(mut self, separator: Option<char>) -> CshFormat {
+        self.thousands_separator = separator;
+        self
+    }
+
+    /// Returns the currency symbol.
+    #[must_use]
+    pub fn get_symbol(&self) -> &str { &self.symbol }
+
+    /// Returns whether the currency symbol goes before the amount.
+    #[must_use]
+    pub fn get_symbol_before(&self) -> bool { self.symbol_before }
+
+    /// Returns the character that separates groups of thousands.
+    #[must_use]
+    pub fn get_thousands_separator(&self) -> Option<char> { self.thousands_separator }
+
+    /// Returns the format used to display [`Csh`] by default.
+    #[must_use]
+    pub fn get_default() -> CshFormat {
+        DEFAULT_FORMAT
+            .read()
+            .ok()
+            .and_then(|format| format.clone())
+            .unwrap_or_default()
+    }
+
+    /// Sets the format used to display [`Csh`] by default, such as in the
+    /// `Display` implementations of [`Csh`] and the transaction logs. Parsing
+    /// with [`FromStr`] is not affected.
+    pub fn set_default(format: CshFormat) {
+        if let Ok(mut default) = DEFAULT_FORMAT.write() {
+            *default = Some(format);
+        }
+    }
+
+    /// Formats `amount` with this format.
+    #[must_use]
+    pub fn format(&self, amount: Csh) -> String {
+        let number = self.format_number(amount);
+
+        if self.symbol.is_empty() {
+            number
+        } else if self.symbol_before {
+            format!("{}{number}", self.symbol)
+        } else {
+            format!("{number} {}", self.symbol)
+        }
+    }
+
+    /// Formats `amount` with this format, leaving out the currency symbol.
+    #[must_use]
+    pub fn format_number(&self, amount: Csh) -> String {
+        let digits = amount.0.to_string();
+        let Some(separator) = self.thousands_separator else {
+            return digits;
+        };
+
+        // the digits are all ASCII, so every chunk is valid UTF-8.
+        digits
+            .as_bytes()
+            .rchunks(3)
+            .rev()
+            .map(|group| std::str::from_utf8(group).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(&separator.to_string())
+    }
+
+    /// Parses an amount of CSH written by a person, such as `"1,250"`,
+    /// `"1.2k"` or `"3M CSH"`.
+    ///
+    /// Groups of thousands can be separated by this format's separator, as
+    /// well as by underscores, spaces and apostrophes, as long as every group
+    /// after the first has exactly 3 digits, so `1,250` is accepted but `12,50`
+    /// is not. The amount can instead end in `k`, `m` or `b` for thousands,
+    /// millions and billions, and in that case can have a fractional part, as
+    /// long as the result is a whole amount. Separators cannot be combined
+    /// with these suffixes.
+    /// The decimal point is `.`, or `,` if this format separates thousands
+    /// with `.`. The currency symbol of this format, or `CSH`, is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`CshError`] if `input` is not an amount, is negative, is
+    /// not a whole amount or is larger than [`Csh::MAX`].
+    pub fn parse(&self, input: &str) -> Result<Csh, CshError> {
+        let decimal_point = self.decimal_point();
+        let mut amount = input.trim();
+        for symbol in [self.symbol.as_str(), "CSH"] {
+            if symbol.is_empty() {
+                continue;
+            }
+            amount = strip_prefix_ignore_case(amount, symbol)
+                .or_else(|| strip_suffix_ignore_case(amount, symbol))
+                .map_or(amount, str::trim);
+        }
+
+        if amount.is_empty() {
+            return Err(CshError::Empty);
+        }
+        if amount.starts_with('-') {
+            return Err(CshError::Negative);
+        }
+        let amount = amount.strip_prefix('+').unwrap_or(amount);
+
+        let (amount, multiplier) = match amount.chars().last() {
+            Some('k' | 'K') => (&amount[..amount.len() - 1], 1_000),
+            Some('m' | 'M') => (&amount[..amount.len() - 1], 1_000_000),
+            Some('b' | 'B') => (&amount[..amount.len() - 1], 1_000_000_000),
+            _ => (amount, 1),
+        };
+        let (whole, fraction) = match amount.trim_end().split_once(decimal_point) {
+            Some((whole, fraction)) => (whole, fraction),
+            None => (amount.trim_end(), ""),
+        };
+
+        let invalid = || CshError::Invalid(input.into());
+        let whole = self.ungroup(whole, multiplier == 1).ok_or_else(invalid)?;
+        if (whole.is_empty() && fraction.is_empty())
+            || !whole.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let whole = if whole.is_empty() {
+            0
+        } else {
+            whole.parse::<u128>().map_err(|_| CshError::OutOfRange)?
+        };
+        let fraction = fraction.trim_end_matches('0');
+        let fraction_value = if fraction.is_empty() {
+            0
+        } else {
+            let scale = u32::try_from(fraction.len())
+                .ok()
+                .and_then(|len| 10_u128.checked_pow(len))
+                .ok_or_else(invalid)?;
+            let value = fraction
+                .parse::<u128>()
+                .ok()
+                .and_then(|fraction| fraction.checked_mul(multiplier))
+                .ok_or_else(invalid)?;
+            if value % scale != 0 {
+                return Err(CshError::Fractional(input.into()));
+            }
+            value / scale
+        };
+
+        whole
+            .checked_mul(multiplier)
+            .and_then(|whole| whole.checked_add(fraction_value))
+            .and_then(|amount| u32::try_from(amount).ok())
+            .map(Csh)
+            .ok_or(CshError::OutOfRange)
+    }
+
+    /// Returns the decimal point used when parsing with this format.
+    fn decimal_point(&self) -> char {
+        if self.thousands_separator == Some('.') {
+            ','
+        } else {
+            '.'
+        }
+    }
+
+    /// Removes the separators between the groups of thousands in the `whole`
+    /// part of an amount. Returns `None` if the separators are not allowed, or
+    /// if they don't separate groups of exactly 3 digits.
+    fn ungroup(&self, whole: &str, allow_separators: bool) -> Option<String> {
+        let decimal_point = self.decimal_point();
+        let Some(separator) = whole.chars().find(|&c| {
+            c != decimal_point
+                && (Some(c) == self.thousands_separator
+                    || matches!(c, '_' | ' ' | '\'' | '\u{a0}' | '\u{202f}'))
+        }) else {
+            return Some(whole.to_owned());
+        };
+        if !allow_separators {
+            return None;
+        }
+
+        let mut groups = whole.split(separator);
+        let first = groups.next().unwrap_or_default();
+        let mut digits = first.to_owned();
+        if first.is_empty() || first.len() > 3 {
+            return None;
+        }
+        for group in groups {
+            if group.len() != 3 {
+                return None;
+            }
+            digits.push_str(group);
+        }
+
+        Some(digits)
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    s.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &s[prefix.len()..])
+}
+
+fn strip_suffix_ignore_case<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let start = s.len().checked_sub(suffix.len())?;
+    s.get(start..)
+        .filter(|end| end.eq_ignore_ascii_case(suffix))
+        .map(|_| &s[..start])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<Csh, CshError> { input.parse() }
+
+    #[test]
+    fn parses_grouped_amounts() {
+        assert_eq!(parse("1,250"), Ok(Csh::new(1_250)));
+        assert_eq!(parse("1,250,000"), Ok(Csh::new(1_250_000)));
+        assert_eq!(parse("1_000_000"), Ok(Csh::new(1_000_000)));
+        assert_eq!(parse("1 000"), Ok(Csh::new(1_000)));
+        assert_eq!(parse("1,000.00"), Ok(Csh::new(1_000)));
+        assert_eq!(parse("1,250 CSH"), Ok(Csh::new(1_250)));
+    }
+
+    #[test]
+    fn parses_suffixed_amounts() {
+        assert_eq!(parse("1.2k"), Ok(Csh::new(1_200)));
+        assert_eq!(parse(".5k"), Ok(Csh::new(500)));
+        assert_eq!(parse("3M CSH"), Ok(Csh::new(3_000_000)));
+        assert_eq!(parse("4.2b"), Ok(Csh::new(4_200_000_000)));
+        assert_eq!(parse("4.3b"), Err(CshError::OutOfRange));
+        assert_eq!(
+            parse("1.2345k"),
+            Err(CshError::Fractional("1.2345k".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_misplaced_separators() {
+        for input in [
+            "12,34", "1,5k", "1,2,3", ",5", "1 0 0", "1,0000", "1,000k", "1,_000",
+        ] {
+            assert_eq!(
+                parse(input),
+                Err(CshError::Invalid(input.into())),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        assert_eq!(parse(""), Err(CshError::Empty));
+        assert_eq!(parse("-5"), Err(CshError::Negative));
+        assert_eq!(parse("1.25"), Err(CshError::Fractional("1.25".into())));
+        assert_eq!(parse("abc"), Err(CshError::Invalid("abc".into())));
+    }
+
+    #[test]
+    fn parses_with_locale_separators() {
+        let format = CshFormat::for_locale("de-DE");
+        assert_eq!(format.parse("1.250"), Ok(Csh::new(1_250)));
+        assert_eq!(format.parse("1,5k"), Ok(Csh::new(1_500)));
+        assert_eq!(
+            format.parse("12.50"),
+            Err(CshError::Invalid("12.50".into()))
+        );
+    }
+
+    #[test]
+    fn parses_with_the_fixed_format() {
+        let locale = CshFormat::for_locale("de-DE");
+        for input in ["1.5k", "1,250"] {
+            assert_eq!(parse(input), CshFormat::default().parse(input));
+            assert_ne!(parse(input), locale.parse(input));
+        }
+    }
+
+    #[test]
+    fn formats_amounts() {
+        let format = CshFormat::default();
+        assert_eq!(format.format(Csh::new(0)), "0 CSH");
+        assert_eq!(format.format(Csh::new(999)), "999 CSH");
+        assert_eq!(format.format(Csh::new(1_234_567)), "1,234,567 CSH");
+        let format = format
+            .symbol("$")
+            .symbol_before(true)
+            .thousands_separator(None);
+        assert_eq!(format.format(Csh::new(1_234_567)), "$1234567");
+    }
+
+    #[test]
+    fn checks_arithmetic() {
+        assert_eq!(Csh::MAX.checked_add(Csh::new(1)), None);
+        assert_eq!(Csh::ZERO.checked_sub(Csh::new(1)), None);
+        assert_eq!(Csh::new(5).checked_add_signed(-6), None);
+        assert_eq!(Csh::new(5).checked_add_signed(-5), Some(Csh::ZERO));
+        assert_eq!(Csh::new(10).delta_to(Csh::new(4)), -6);
+        assert_eq!(Csh::try_from(-1_i64), Err(CshError::Negative));
+        assert_eq!(
+            Csh::try_from(u64::from(u32::MAX) + 1),
+            Err(CshError::OutOfRange)
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! This module contains the [`CCashApi`] trait, which abstracts over the user
+//! and admin operations of the `CCash` API. [`CCashSession`] implements it by
+//! calling the functions in [`methods`] and [`methods::admin`], but code that
+//! depends on `CCashApi` rather than on these functions directly can swap in
+//! fakes, caching decorators or instrumented wrappers without an HTTP server.
+//!
+//! ```no_run
+//! use ccash_rs::{api::CCashApi, CCashUser, Csh, Result};
+//!
+//! async fn pay_rent(bank: &dyn CCashApi, tenant: &CCashUser) -> Result<Csh> {
+//!     bank.send_funds(tenant, "landlord", Csh::new(100)).await
+//! }
+//! ```
+
+use crate::{methods, CCashSession, CCashUser, Csh, Result, TransactionLogV2, Username};
+use async_trait::async_trait;
+
+/// Trait for all the user and admin operations of the `CCash` API. The
+/// deprecated [`get_log`](methods::get_log) is not part of this trait; use
+/// [`get_logs`](CCashApi::get_logs), which falls back to it on instances that
+/// don't support [`get_log_v2`](CCashApi::get_log_v2).
+///
+/// Each method is documented by the function in [`methods`] or
+/// [`methods::admin`] of the same name, which is what [`CCashSession`] calls.
+#[async_trait]
+pub trait CCashApi: Send + Sync {
+    /// Returns the balance of the `user`. See [`methods::get_balance`].
+    async fn get_balance(&self, user: &CCashUser) -> Result<Csh>;
+
+    /// Returns the transaction logs of the `user`. See
+    /// [`methods::get_log_v2`].
+    async fn get_log_v2(&self, user: &CCashUser) -> Result<Vec<TransactionLogV2>>;
+
+    /// Returns the transaction logs of the `user` from the best endpoint
+    /// supported by the `CCash` instance. See [`methods::get_logs`].
+    async fn get_logs(&self, user: &CCashUser) -> Result<Vec<TransactionLogV2>>;
+
+    /// Returns whether the `user` exists. See [`methods::contains_user`].
+    async fn contains_user(&self, user: &CCashUser) -> Result<bool>;
+
+    /// Returns whether the password of the `user` is correct. See
+    /// [`methods::verify_password`].
+    async fn verify_password(&self, user: &CCashUser) -> Result<bool>;
+
+    /// Changes the password of the `user`. See [`methods::change_password`].
+    async fn change_password(
+        &self,
+        user: &mut CCashUser,
+        new_password: &str,
+    ) -> Result<bool>;
+
+    /// Sends `amount` from the `user` to the user named `recipient_name`. See
+    /// [`methods::send_funds`].
+    async fn send_funds(
+        &self,
+        user: &CCashUser,
+        recipient_name: &str,
+        amount: Csh,
+    ) -> Result<Csh>;
+
+    /// Adds the `user` with a balance of 0. See [`methods::add_user`].
+    async fn add_user(&self, user: &CCashUser) -> Result<bool>;
+
+    /// Deletes the `user`. See [`methods::delete_user`].
+    async fn delete_user(&self, user: &CCashUser) -> Result<()>;
+
+    /// Returns whether the `user` is the admin account. See
+    /// [`methods::admin::verify_account`].
+    async fn admin_verify_account(&self, user: &CCashUser) -> Result<bool>;
+
+    /// Changes the password of the `user` as the admin. See
+    /// [`methods::admin::change_password`].
+    async fn admin_change_password(
+        &self,
+        admin_user: &CCashUser,
+        user: &mut CCashUser,
+        new_password: &str,
+    ) -> Result<bool>;
+
+    /// Sets the balance of the user named `username`. See
+    /// [`methods::admin::set_balance`].
+    async fn admin_set_balance(
+        &self,
+        admin_user: &CCashUser,
+        username: &Username,
+        new_balance: Csh,
+    ) -> Result<()>;
+
+    /// Impacts the balance of the user named `username` by `amount`. See
+    /// [`methods::admin::impact_balance`].
+    async fn admin_impact_balance(
+        &self,
+        admin_user: &CCashUser,
+        username: &Username,
+        amount: i64,
+    ) -> Result<()>;
+
+    /// Adds the `new_user` with a balance of `amount`. See
+    /// [`methods::admin::add_user`].
+    async fn admin_add_user(
+        &self,
+        admin_user: &CCashUser,
+        new_user: &CCashUser,
+        amount: Csh,
+    ) -> Result<bool>;
+
+    /// Deletes the user named `username`. See
+    /// [`methods::admin::delete_user`].
+    async fn admin_delete_user(
+        &self,
+        admin_user: &CCashUser,
+        username: &Username,
+    ) -> Result<()>;
+
+    /// Prunes users with less than `amount` in balance. See
+    /// [`methods::admin::prune_users`].
+    async fn admin_prune_users(
+        &self,
+        admin_user: &CCashUser,
+        amount: Csh,
+        time: Option<i64>,
+    ) -> Result<u64>;
+
+    /// Saves and closes the `CCash` instance. See [`methods::admin::close`].
+    async fn admin_close(&mut self, admin_user: &CCashUser) -> Result<()>;
+}
+
+#[async_trait]
+impl CCashApi for CCashSession {
+    async fn get_balance(&self, user: &CCashUser) -> Result<Csh> {
+        methods::get_balance(self, user).await
+    }
+
+    async fn get_log_v2(&self, user: &CCashUser) -> Result<Vec<TransactionLogV2>> {
+        methods::get_log_v2(self, user).await
+    }
+
+    async fn get_logs(&self, user: &CCashUser) -> Result<Vec<TransactionLogV2>> {
+        methods::get_logs(self, user).await
+    }
+
+    async fn contains_user(&self, user: &CCashUser) -> Result<bool> {
+        methods::contains_user(self, user).await
+    }
+
+    async fn verify_password(&self, user: &CCashUser) -> Result<bool> {
+        methods::verify_password(self, user).await
+    }
+
+    async fn change_password(
+        &self,
+        user: &mut CCashUser,
+        new_password: &str,
+    ) -> Result<bool> {
+        methods::change_password(self, user, new_password).await
+    }
+
+    async fn send_funds(
+        &self,
+        user: &CCashUser,
+        recipient_name: &str,
+        amount: Csh,
+    ) -> Result<Csh> {
+        methods::send_funds(self, user, recipient_name, amount).await
+    }
+
+    async fn add_user(&self, user: &CCashUser) -> Result<bool> {
+        methods::add_user(self, user).await
+    }
+
+    async fn delete_user(&self, user: &CCashUser) -> Result<()> {
+        methods::delete_user(self, user).await
+    }
+
+    async fn admin_verify_account(&self, user: &CCashUser) -> Result<bool> {
+        methods::admin::verify_account(self, user).await
+    }
+
+    async fn admin_change_password(
+        &self,
+        admin_user: &CCashUser,
+        user: &mut CCashUser,
+        new_password: &str,
+    ) -> Result<bool> {
+        methods::admin::change_password(self, admin_user, user, new_password).await
+    }
+
+    async fn admin_set_balance(
+        &self,
+        admin_user: &CCashUser,
+        username: &Username,
+        new_balance: Csh,
+    ) -> Result<()> {
+        methods::admin::set_balance(self, admin_user, username, new_balance).await
+    }
+
+    async fn admin_impact_balance(
+        &self,
+        admin_user: &CCashUser,
+        username: &Username,
+        amount: i64,
+    ) -> Result<()> {
+        methods::admin::impact_balance(self, admin_user, username, amount).await
+    }
+
+    async fn admin_add_user(
+        &self,
+        admin_user: &CCashUser,
+        new_user: &CCashUser,
+        amount: Csh,
+    ) -> Result<bool> {
+        methods::admin::add_user(self, admin_user, new_user, amount).await
+    }
+
+    async fn admin_delete_user(
+        &self,
+        admin_user: &CCashUser,
+        username: &Username,
+    ) -> Result<()> {
+        methods::admin::delete_user(self, admin_user, username).await
+    }
+
+    async fn admin_prune_users(
+        &self,
+        admin_user: &CCashUser,
+        amount: Csh,
+        time: Option<i64>,
+    ) -> Result<u64> {
+        methods::admin::prune_users(self, admin_user, amount, time).await
+    }
+
+    async fn admin_close(&mut self, admin_user: &CCashUser) -> Result<()> {
+        methods::admin::close(self, admin_user).await
+    }
+}
@@ -0,0 +1,703 @@
+//! `ccash`, a command-line client for `CCash` instances.
+//!
+//! Every non-admin function in `ccash_rs::methods` and every admin function in
+//! `ccash_rs::methods::admin` is available as a subcommand. The instance URL
+//! and credentials are read from flags, then from the `CCASH_URL`,
+//! `CCASH_USER`, `CCASH_PASSWORD` and `CCASH_PASSWORD_FILE` environment
+//! variables, then from the profile named by `--profile` or the default profile
+//! in the profiles config file, and otherwise the user is prompted for them.
+//! `--json` prints the result as JSON for use in scripts.
+
+mod shell;
+
+use ccash_rs::{
+    bulk::{self, ProvisionOptions, ProvisionOutcome, ProvisionReport, ProvisionStatus},
+    methods,
+    profile::{Profile, ProfileConfig},
+    query::{LogQuery, SortOrder},
+    CCashApiError, CCashError, CCashSession, CCashUser, Csh, Password, TransactionLogV2,
+    Username,
+};
+use chrono::{Duration, Utc};
+use clap::{Args, Parser, Subcommand};
+use serde_json::{json, Value};
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// Command-line client for CCash instances.
+#[derive(Debug, Parser)]
+#[command(name = "ccash", version)]
+struct Cli {
+    #[command(flatten)]
+    connection: Connection,
+
+    /// Print the result as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Args)]
+struct Connection {
+    /// The base URL of the CCash instance.
+    #[arg(long, env = "CCASH_URL", global = true)]
+    url: Option<String>,
+
+    /// The name of the user to authenticate as.
+    #[arg(short, long, env = "CCASH_USER", global = true)]
+    user: Option<String>,
+
+    /// The password of the user. Prompted for if not given.
+    #[arg(long, env = "CCASH_PASSWORD", hide_env_values = true, global = true)]
+    password: Option<String>,
+
+    /// A file to read the password of the user from.
+    #[arg(long, env = "CCASH_PASSWORD_FILE", global = true)]
+    password_file: Option<PathBuf>,
+
+    /// The profile to use from the profiles config file, instead of the
+    /// default profile.
+    #[arg(long, env = "CCASH_PROFILE", global = true)]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Show the properties of the CCash instance.
+    Properties,
+    /// Show the balance of a user, or of the authenticated user.
+    Balance {
+        /// The name of the user.
+        name: Option<String>,
+    },
+    /// Show the transaction logs of the authenticated user.
+    Log(LogArgs),
+    /// Check whether a user exists.
+    Exists {
+        /// The name of the user.
+        name: String,
+    },
+    /// Check whether the password of the authenticated user is correct.
+    VerifyPassword,
+    /// Send funds from the authenticated user to another user.
+    Send {
+        /// The name of the recipient.
+        to: String,
+        /// The amount of CSH to send, such as `250`, `1,250` or `1.2k`.
+        amount: Csh,
+    },
+    /// Register the authenticated user with a balance of 0.
+    Register,
+    /// Change the password of the authenticated user.
+    ChangePassword {
+        /// The new password. Prompted for if not given.
+        #[arg(long)]
+        new_password: Option<String>,
+    },
+    /// Delete the authenticated user.
+    Delete,
+    /// Admin commands, run as the authenticated user.
+    #[command(subcommand)]
+    Admin(AdminCommand),
+    /// Start an interactive shell for administering the CCash instance as the
+    /// authenticated user.
+    Shell,
+}
+
+#[derive(Debug, Args)]
+struct LogArgs {
+    /// Only show transactions made within this long ago, such as `30m`, `12h`
+    /// or `1d`.
+    #[arg(long, value_parser = parse_age)]
+    since: Option<Duration>,
+    /// Only show transactions made before this long ago.
+    #[arg(long, value_parser = parse_age)]
+    until: Option<Duration>,
+    /// Only show transactions with this user.
+    #[arg(long)]
+    counterparty: Option<String>,
+    /// Only show the newest `limit` transactions.
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+impl LogArgs {
+    fn query(&self) -> LogQuery {
+        let now = Utc::now();
+        let mut query = LogQuery::new().order(SortOrder::NewestFirst);
+
+        if let Some(since) = self.since {
+            query = query.since(now - since);
+        }
+        if let Some(until) = self.until {
+            query = query.until(now - until);
+        }
+        if let Some(counterparty) = &self.counterparty {
+            query = query.counterparty(counterparty);
+        }
+        if let Some(limit) = self.limit {
+            query = query.limit(limit);
+        }
+
+        query
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum AdminCommand {
+    /// Check whether the authenticated user is the admin account.
+    Verify,
+    /// Change the password of a user.
+    ChangePassword {
+        /// The name of the user.
+        name: String,
+        /// The new password. Prompted for if not given.
+        #[arg(long)]
+        new_password: Option<String>,
+    },
+    /// Set the balance of a user.
+    SetBalance {
+        /// The name of the user.
+        name: Username,
+        /// The new balance in CSH.
+        balance: Csh,
+    },
+    /// Add to or take from the balance of a user.
+    Impact {
+        /// The name of the user.
+        name: Username,
+        /// The amount of CSH to add, or to take if negative.
+        #[arg(allow_hyphen_values = true, value_parser = parse_delta)]
+        amount: i64,
+    },
+    /// Add a user with a starting balance.
+    AddUser {
+        /// The name of the user.
+        name: String,
+        /// The starting balance in CSH.
+        #[arg(long, default_value_t = Csh::ZERO)]
+        balance: Csh,
+        /// The password of the new user. Prompted for if not given.
+        #[arg(long)]
+        new_password: Option<String>,
+    },
+    /// Delete a user.
+    DeleteUser {
+        /// The name of the user.
+        name: Username,
+    },
+    /// Delete users with less than an amount of CSH.
+    Prune {
+        /// Users with less than this balance are pruned.
+        amount: Csh,
+        /// Only prune users that have not made a transaction since this Unix
+        /// epoch time.
+        #[arg(long)]
+        time: Option<i64>,
+    },
+    /// Create users from a CSV file with `username`, `password` and optional
+    /// `balance` columns.
+    Provision {
+        /// The CSV file to read.
+        file: PathBuf,
+        /// Validate the file and check which users already exist, without
+        /// creating any users.
+        #[arg(long)]
+        dry_run: bool,
+        /// The max amount of requests made at a time.
+        #[arg(long, default_value_t = 8)]
+        concurrency: usize,
+    },
+    /// Save and shut down the CCash instance.
+    Shutdown,
+}
+
+/// The result of a command, printed as `text` or as `json` with `--json`.
+struct Reply {
+    text: String,
+    json: Value,
+}
+
+impl Reply {
+    fn new(text: impl Into<String>, json: Value) -> Reply {
+        Reply {
+            text: text.into(),
+            json,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli.connection, cli.command, cli.json).await {
+        Ok(reply) if cli.json => println!("{}", reply.json),
+        Ok(reply) => println!("{}", reply.text),
+        Err(e) => {
+            if cli.json {
+                println!("{}", json!({ "error": e.to_string() }));
+            } else {
+                eprintln!("error: {e}");
+            }
+            return ExitCode::FAILURE;
+        },
+    }
+
+    ExitCode::SUCCESS
+}
+
+async fn run(connection: Connection, command: Command, json: bool) -> CliResult<Reply> {
+    let connection = connection.login()?;
+    let mut session = CCashSession::new(&connection.url()?);
+    session.establish_connection().await?;
+
+    let reply = match command {
+        Command::Properties => {
+            let properties = session
+                .get_properties()
+                .clone()
+                .ok_or("the CCash instance did not return its properties")?;
+            let version = properties
+                .get_version()
+                .map_or_else(|| "unknown".into(), |v| v.to_string());
+            let return_on_delete = properties
+                .get_return_on_delete_account()
+                .as_deref()
+                .unwrap_or("none");
+            Reply::new(
+                [
+                    format!("version: {version}"),
+                    format!("max log: {}", properties.get_max_log()),
+                    format!("registration open: {}", properties.get_add_user_is_open()),
+                    format!("return on delete: {return_on_delete}"),
+                ]
+                .join("\n"),
+                serde_json::to_value(&properties)?,
+            )
+        },
+        Command::Balance { name } => {
+            let user = match name {
+                Some(name) => CCashUser::new(&name, "")?,
+                None => CCashUser::new(&connection.name()?, "")?,
+            };
+            balance_reply(&session, &user).await?
+        },
+        Command::Log(args) => {
+            let user = connection.user()?;
+            let logs =
+                methods::get_log_v2_filtered(&session, &user, &args.query()).await?;
+            logs_reply(&logs)?
+        },
+        Command::Exists { name } => exists_reply(&session, &name).await?,
+        Command::VerifyPassword => {
+            let user = connection.user()?;
+            let correct = methods::verify_password(&session, &user).await?;
+            Reply::new(
+                if correct {
+                    "password is correct"
+                } else {
+                    "password is incorrect"
+                },
+                json!({ "name": user.get_username(), "correct": correct }),
+            )
+        },
+        Command::Send { to, amount } => {
+            let user = connection.user()?;
+            let balance = methods::send_funds(&session, &user, &to, amount).await?;
+            Reply::new(
+                format!(
+                    "sent {amount} to {to}, {} now has {balance}",
+                    user.get_username()
+                ),
+                json!({ "to": to, "amount": amount, "balance": balance }),
+            )
+        },
+        Command::Register => {
+            let user = connection.user()?;
+            let created = methods::add_user(&session, &user).await?;
+            created_reply(&user, created)
+        },
+        Command::ChangePassword { new_password } => {
+            let mut user = connection.user()?;
+            let new_password = new_password_or_prompt(new_password)?;
+            let changed =
+                methods::change_password(&session, &mut user, &new_password).await?;
+            changed_reply(&user, changed)
+        },
+        Command::Delete => {
+            let user = connection.user()?;
+            methods::delete_user(&session, &user).await?;
+            deleted_reply(user.get_username())
+        },
+        Command::Admin(command) =>
+            run_admin(&mut session, connection.user()?, command).await?,
+        Command::Shell => shell::run(&mut session, connection.user()?, json).await?,
+    };
+
+    Ok(reply)
+}
+
+async fn run_admin(
+    session: &mut CCashSession,
+    admin: CCashUser,
+    command: AdminCommand,
+) -> CliResult<Reply> {
+    let reply = match command {
+        AdminCommand::Verify => {
+            let is_admin = methods::admin::verify_account(session, &admin).await?;
+            Reply::new(
+                format!(
+                    "{} {} the admin account",
+                    admin.get_username(),
+                    if is_admin { "is" } else { "is not" }
+                ),
+                json!({ "name": admin.get_username(), "admin": is_admin }),
+            )
+        },
+        AdminCommand::ChangePassword { name, new_password } => {
+            let mut user = CCashUser::new(&name, "")?;
+            let new_password = new_password_or_prompt(new_password)?;
+            let changed = methods::admin::change_password(
+                session,
+                &admin,
+                &mut user,
+                &new_password,
+            )
+            .await?;
+            changed_reply(&user, changed)
+        },
+        AdminCommand::SetBalance { name, balance } => {
+            methods::admin::set_balance(session, &admin, &name, balance).await?;
+            Reply::new(
+                format!("set the balance of {name} to {balance}"),
+                json!({ "name": name, "balance": balance }),
+            )
+        },
+        AdminCommand::Impact { name, amount } => {
+            methods::admin::impact_balance(session, &admin, &name, amount).await?;
+            let change = Csh::try_from(amount.unsigned_abs()).unwrap_or(Csh::MAX);
+            Reply::new(
+                if amount < 0 {
+                    format!("took {change} from the balance of {name}")
+                } else {
+                    format!("added {change} to the balance of {name}")
+                },
+                json!({ "name": name, "amount": amount }),
+            )
+        },
+        AdminCommand::AddUser {
+            name,
+            balance,
+            new_password,
+        } => {
+            let password = match new_password {
+                Some(password) => password,
+                None => prompt_password(&format!("Password for {name}"))?,
+            };
+            let user = CCashUser::new(&name, &password)?;
+            let created =
+                methods::admin::add_user(session, &admin, &user, balance).await?;
+            created_reply(&user, created)
+        },
+        AdminCommand::DeleteUser { name } => {
+            methods::admin::delete_user(session, &admin, &name).await?;
+            deleted_reply(name.as_str())
+        },
+        AdminCommand::Prune { amount, time } => {
+            let pruned =
+                methods::admin::prune_users(session, &admin, amount, time).await?;
+            Reply::new(
+                format!("pruned {pruned} users"),
+                json!({ "pruned": pruned }),
+            )
+        },
+        AdminCommand::Provision {
+            file,
+            dry_run,
+            concurrency,
+        } => {
+            let options = ProvisionOptions::new()
+                .dry_run(dry_run)
+                .concurrency(concurrency);
+            let report =
+                bulk::provision_users(session, &admin, File::open(file)?, &options)
+                    .await?;
+            provision_reply(&report)
+        },
+        AdminCommand::Shutdown => {
+            methods::admin::close(session, &admin).await?;
+            Reply::new(
+                "the CCash instance was shut down",
+                json!({ "closed": true }),
+            )
+        },
+    };
+
+    Ok(reply)
+}
+
+impl Connection {
+    /// Returns the profile described by the flags and environment variables,
+    /// falling back to the profile from the profiles config file.
+    fn login(self) -> CliResult<Login> {
+        let mut flags = Profile::new();
+        if let Some(url) = &self.url {
+            flags = flags.with_url(url)?;
+        }
+        if let Some(user) = &self.user {
+            flags = flags.with_user(user);
+        }
+        if let Some(password) = self.password {
+            flags = flags.with_password(password);
+        }
+        if let Some(path) = self.password_file {
+            flags = flags.with_password_file(path);
+        }
+
+        let config = ProfileConfig::load_default()?;
+        let profile = match &self.profile {
+            Some(name) => config.get(name)?,
+            None => config.default_profile()?.unwrap_or_default(),
+        };
+
+        Ok(Login(flags.merge(profile)))
+    }
+}
+
+/// The resolved profile to connect and authenticate with.
+struct Login(Profile);
+
+impl Login {
+    /// Returns the URL of the CCash instance, prompting for it if it was not
+    /// given.
+    fn url(&self) -> io::Result<String> {
+        match self.0.get_url() {
+            Some(url) => Ok(url.into()),
+            None => prompt("CCash URL"),
+        }
+    }
+
+    /// Returns the name of the user to authenticate as, prompting for it if
+    /// it was not given.
+    fn name(&self) -> io::Result<String> {
+        match self.0.get_user() {
+            Some(name) => Ok(name.into()),
+            None => prompt("Username"),
+        }
+    }
+
+    /// Returns the user to authenticate as, prompting for the name and
+    /// password if they were not given.
+    fn user(&self) -> CliResult<CCashUser> {
+        let name = self.name()?;
+        let password = match self.0.password()? {
+            Some(password) => password,
+            None => Password::new(prompt_password(&format!("Password for {name}"))?),
+        };
+
+        Ok(CCashUser::new(&name, password.expose_secret())?)
+    }
+}
+
+async fn balance_reply(session: &CCashSession, user: &CCashUser) -> CliResult<Reply> {
+    let balance = methods::get_balance(session, user).await?;
+    Ok(Reply::new(
+        format!("{} has {balance}", user.get_username()),
+        json!({ "name": user.get_username(), "balance": balance }),
+    ))
+}
+
+async fn exists_reply(session: &CCashSession, name: &str) -> CliResult<Reply> {
+    let user = CCashUser::new(name, "")?;
+    let exists = match methods::contains_user(session, &user).await {
+        Err(CCashError::ApiError(CCashApiError::UserNotFound { .. })) => false,
+        result => result?,
+    };
+    Ok(Reply::new(
+        format!(
+            "{} {}",
+            user.get_username(),
+            if exists { "exists" } else { "does not exist" }
+        ),
+        json!({ "name": user.get_username(), "exists": exists }),
+    ))
+}
+
+fn logs_reply(logs: &[TransactionLogV2]) -> CliResult<Reply> {
+    let text = if logs.is_empty() {
+        "no transactions".into()
+    } else {
+        logs.iter()
+            .map(TransactionLogV2::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Ok(Reply::new(text, serde_json::to_value(logs)?))
+}
+
+fn provision_reply(report: &ProvisionReport) -> Reply {
+    let mut lines = Vec::new();
+    let mut rows = Vec::new();
+
+    for result in report.get_results() {
+        let (status, reason) = match result.get_outcome() {
+            ProvisionOutcome::Created => ("created", None),
+            ProvisionOutcome::WouldCreate => ("would create", None),
+            ProvisionOutcome::AlreadyExists => ("already exists", None),
+            ProvisionOutcome::Invalid(reason) => ("invalid", Some(reason.clone())),
+            ProvisionOutcome::Failed(e) => ("failed", Some(e.to_string())),
+        };
+
+        lines.push(match &reason {
+            Some(reason) => format!(
+                "line {}: {} {status}: {reason}",
+                result.get_line(),
+                result.get_username()
+            ),
+            None => format!(
+                "line {}: {} {status}",
+                result.get_line(),
+                result.get_username()
+            ),
+        });
+        rows.push(json!({
+            "line": result.get_line(),
+            "name": result.get_username(),
+            "balance": result.get_balance(),
+            "status": status.replace(' ', "_"),
+            "reason": reason,
+        }));
+    }
+
+    let counts = [
+        ("created", ProvisionStatus::Created),
+        ("would_create", ProvisionStatus::WouldCreate),
+        ("already_exists", ProvisionStatus::AlreadyExists),
+        ("invalid", ProvisionStatus::Invalid),
+        ("failed", ProvisionStatus::Failed),
+    ]
+    .map(|(name, status)| (name, report.count(status)));
+
+    lines.push(
+        counts
+            .iter()
+            .map(|(name, count)| format!("{count} {}", name.replace('_', " ")))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+
+    let mut summary = counts
+        .iter()
+        .map(|&(name, count)| (name.to_owned(), json!(count)))
+        .collect::<serde_json::Map<_, _>>();
+    summary.insert("rows".into(), Value::Array(rows));
+
+    Reply::new(lines.join("\n"), Value::Object(summary))
+}
+
+fn created_reply(user: &CCashUser, created: bool) -> Reply {
+    Reply::new(
+        format!(
+            "{} {}",
+            user.get_username(),
+            if created {
+                "was created"
+            } else {
+                "already exists"
+            }
+        ),
+        json!({ "name": user.get_username(), "created": created }),
+    )
+}
+
+fn changed_reply(user: &CCashUser, changed: bool) -> Reply {
+    Reply::new(
+        if changed {
+            format!("changed the password of {}", user.get_username())
+        } else {
+            format!("could not change the password of {}", user.get_username())
+        },
+        json!({ "name": user.get_username(), "changed": changed }),
+    )
+}
+
+fn deleted_reply(name: &str) -> Reply {
+    Reply::new(
+        format!("deleted {name}"),
+        json!({ "name": name, "deleted": true }),
+    )
+}
+
+fn new_password_or_prompt(new_password: Option<String>) -> CliResult<String> {
+    if let Some(new_password) = new_password {
+        return Ok(new_password);
+    }
+
+    let new_password = prompt_password("New password")?;
+    if prompt_password("Repeat new password")? != new_password {
+        return Err("the passwords do not match".into());
+    }
+
+    Ok(new_password)
+}
+
+/// Parses a signed amount of CSH such as `250`, `-1,250` or `+1.2k`.
+fn parse_delta(delta: &str) -> Result<i64, String> {
+    let delta = delta.trim();
+    match delta.strip_prefix('-') {
+        Some(amount) => amount.parse::<Csh>().map(Csh::to_negative_delta),
+        None => delta.parse::<Csh>().map(Csh::to_delta),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Parses an age such as `90s`, `30m`, `12h`, `1d` or `2w`. A number without
+/// a unit is in seconds.
+fn parse_age(age: &str) -> Result<Duration, String> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (amount, unit) = age.split_at(split);
+    let amount = amount
+        .parse::<i64>()
+        .map_err(|_| format!("invalid age \"{age}\", expected e.g. 1d"))?;
+
+    let factor = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => 0,
+    };
+
+    amount
+        .checked_mul(factor)
+        .filter(|&seconds| factor > 0 && seconds <= i64::MAX / 1000)
+        .map(Duration::seconds)
+        .ok_or_else(|| format!("invalid age \"{age}\", expected e.g. 1d"))
+}
+
+fn prompt(message: &str) -> io::Result<String> {
+    let mut stderr = io::stderr();
+    write!(stderr, "{message}: ")?;
+    stderr.flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_owned())
+}
+
+fn prompt_password(message: &str) -> io::Result<String> {
+    rpassword::prompt_password(format!("{message}: "))
+}
@@ -0,0 +1,329 @@
+//! The interactive shell started with `ccash shell`, for administering a
+//! `CCash` instance without connecting and logging in for every command.
+//!
+//! Usernames that have been used in the shell, or seen in logs, are completed
+//! with tab, and destructive commands ask for confirmation first.
+
+use crate::{
+    balance_reply, exists_reply, logs_reply, prompt_password, run_admin, AdminCommand,
+    CliResult, LogArgs, Reply,
+};
+use ccash_rs::{methods, CCashSession, CCashUser};
+use clap::{CommandFactory, Parser, Subcommand};
+use rustyline::{
+    completion::Completer, error::ReadlineError, history::DefaultHistory, Context,
+    Editor, Helper, Highlighter, Hinter, Validator,
+};
+use serde_json::json;
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Parser)]
+#[command(multicall = true)]
+struct ShellLine {
+    #[command(subcommand)]
+    command: ShellCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ShellCommand {
+    /// Show the balance of a user.
+    Balance {
+        /// The name of the user.
+        name: String,
+    },
+    /// Check whether a user exists.
+    Exists {
+        /// The name of the user.
+        name: String,
+    },
+    /// Show the transaction logs of a user. The password of the user is
+    /// prompted for, unless it is the admin account.
+    Log {
+        /// The name of the user. Defaults to the admin account.
+        name: Option<String>,
+        #[command(flatten)]
+        args: LogArgs,
+    },
+    #[command(flatten)]
+    Admin(AdminCommand),
+    /// Leave the shell.
+    #[command(alias = "quit")]
+    Exit,
+}
+
+impl ShellCommand {
+    /// Returns the question to confirm before running this command, if it is
+    /// destructive.
+    fn confirmation(&self) -> Option<String> {
+        match self {
+            ShellCommand::Admin(AdminCommand::DeleteUser { name }) =>
+                Some(format!("Delete {name}?")),
+            ShellCommand::Admin(AdminCommand::Prune { amount, .. }) =>
+                Some(format!("Prune all users with less than {amount}?")),
+            ShellCommand::Admin(AdminCommand::Shutdown) =>
+                Some("Save and shut down the CCash instance?".into()),
+            _ => None,
+        }
+    }
+
+    /// Returns the usernames given to this command.
+    fn names(&self) -> Vec<&str> {
+        match self {
+            ShellCommand::Balance { name }
+            | ShellCommand::Exists { name }
+            | ShellCommand::Log {
+                name: Some(name), ..
+            }
+            | ShellCommand::Admin(
+                AdminCommand::ChangePassword { name, .. }
+                | AdminCommand::AddUser { name, .. },
+            ) => vec![name],
+            ShellCommand::Admin(
+                AdminCommand::SetBalance { name, .. } | AdminCommand::Impact { name, .. },
+            ) => vec![name.as_str()],
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Helper, Hinter, Highlighter, Validator)]
+struct ShellHelper {
+    commands: Vec<String>,
+    names: BTreeSet<String>,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+        let candidates = if line[..start].trim().is_empty() {
+            self.commands.iter().collect::<Vec<_>>()
+        } else {
+            self.names.iter().collect()
+        };
+
+        Ok((
+            start,
+            candidates
+                .into_iter()
+                .filter(|c| c.starts_with(word))
+                .cloned()
+                .collect(),
+        ))
+    }
+}
+
+/// Runs the shell as the `admin` user until it is exited or the `CCash`
+/// instance is shut down.
+pub(crate) async fn run(
+    session: &mut CCashSession,
+    admin: CCashUser,
+    json: bool,
+) -> CliResult<Reply> {
+    if !methods::admin::verify_account(session, &admin).await? {
+        return Err(format!("{} is not the admin account", admin.get_username()).into());
+    }
+
+    let mut names = BTreeSet::from([admin.get_username().to_owned()]);
+    if let Some(properties) = session.get_properties() {
+        names.extend(properties.get_return_on_delete_account().clone());
+    }
+
+    let mut editor = Editor::<ShellHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ShellHelper {
+        commands: ShellLine::command()
+            .get_subcommands()
+            .map(|c| c.get_name().to_owned())
+            .collect(),
+        names,
+    }));
+
+    let history = history_path();
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+
+    eprintln!(
+        "Logged in as {}. Type `help` for a list of commands.",
+        admin.get_username()
+    );
+
+    loop {
+        let line = match editor.readline("ccash> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(history_entry(&line))?;
+
+        let command = match ShellLine::try_parse_from(&words) {
+            Ok(line) => line.command,
+            Err(e) => {
+                e.print()?;
+                continue;
+            },
+        };
+
+        if let ShellCommand::Exit = command {
+            break;
+        }
+
+        if let Some(question) = command.confirmation() {
+            let answer = editor.readline(&format!("{question} [y/N] "))?;
+            if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                eprintln!("Cancelled.");
+                continue;
+            }
+        }
+
+        let is_shutdown = matches!(command, ShellCommand::Admin(AdminCommand::Shutdown));
+        let names = &mut editor
+            .helper_mut()
+            .expect("the helper is set before the loop")
+            .names;
+
+        match execute(session, &admin, command, names).await {
+            Ok(reply) if json => println!("{}", reply.json),
+            Ok(reply) => println!("{}", reply.text),
+            Err(e) if json => println!("{}", json!({ "error": e.to_string() })),
+            Err(e) => eprintln!("error: {e}"),
+        }
+
+        if is_shutdown && !session.is_connected() {
+            break;
+        }
+    }
+
+    if let Some(history) = &history {
+        let _ = save_history(&mut editor, history);
+    }
+
+    Ok(Reply::new("Goodbye.", json!({ "exited": true })))
+}
+
+/// Runs a single `command`, adding the usernames it used to `names` if it
+/// succeeds.
+async fn execute(
+    session: &mut CCashSession,
+    admin: &CCashUser,
+    command: ShellCommand,
+    names: &mut BTreeSet<String>,
+) -> CliResult<Reply> {
+    let used = command
+        .names()
+        .into_iter()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+
+    let reply = match command {
+        ShellCommand::Balance { name } =>
+            balance_reply(session, &CCashUser::new(&name, "")?).await?,
+        ShellCommand::Exists { name } => exists_reply(session, &name).await?,
+        ShellCommand::Log { name, args } => {
+            let user = match name {
+                Some(name) if name.to_lowercase() != admin.get_username() => {
+                    let password = prompt_password(&format!("Password for {name}"))?;
+                    CCashUser::new(&name, &password)?
+                },
+                _ => admin.clone(),
+            };
+            let logs =
+                methods::get_log_v2_filtered(session, &user, &args.query()).await?;
+            names.extend(logs.iter().map(|log| log.get_counterparty().to_owned()));
+            logs_reply(&logs)?
+        },
+        ShellCommand::Admin(command) => {
+            if let AdminCommand::DeleteUser { name } = &command {
+                names.remove(name.as_str());
+            }
+            run_admin(session, admin.clone(), command).await?
+        },
+        ShellCommand::Exit => unreachable!("exit is handled by the shell loop"),
+    };
+
+    names.extend(used);
+    Ok(reply)
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".ccash_history"))
+}
+
+/// Returns the `line` as it is saved in the history, without the value of any
+/// `--new-password` argument so that passwords are never written to disk.
+fn history_entry(line: &str) -> Cow<'_, str> {
+    if !line.contains("--new-password") {
+        return Cow::Borrowed(line);
+    }
+
+    let mut words = line.split_whitespace();
+    let mut kept = Vec::new();
+    while let Some(word) = words.next() {
+        if word == "--new-password" {
+            words.next();
+        } else if !word.starts_with("--new-password=") {
+            kept.push(word);
+        }
+    }
+
+    Cow::Owned(kept.join(" "))
+}
+
+/// Saves the history of the `editor` to `path`, making sure only the current
+/// user can read it.
+fn save_history(
+    editor: &mut Editor<ShellHelper, DefaultHistory>,
+    path: &Path,
+) -> rustyline::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::{
+            fs::{OpenOptions, Permissions},
+            os::unix::fs::{OpenOptionsExt, PermissionsExt},
+        };
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(path)?;
+        std::fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    }
+
+    editor.save_history(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::history_entry;
+
+    #[test]
+    fn leaves_passwords_out_of_history() {
+        assert_eq!(history_entry("balance alice"), "balance alice");
+        assert_eq!(
+            history_entry("admin add-user bob --new-password hunter2 --balance 10"),
+            "admin add-user bob --balance 10"
+        );
+        assert_eq!(
+            history_entry("admin change-password bob --new-password=hunter2"),
+            "admin change-password bob"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! This module contains the [`BlockingCCashSessionBuilder`], used to configure
+//! a [`BlockingCCashSession`] beyond its base URL.
+
+use crate::{
+    api_url, blocking::BlockingCCashSession, builder::ClientConfig,
+    connection::Connection, RetryPolicy,
+};
+use reqwest::{blocking::Client, Certificate, Proxy};
+use std::time::Duration;
+
+/// Builder for a [`BlockingCCashSession`]. Blocking version of
+/// [`CCashSessionBuilder`](crate::CCashSessionBuilder).
+///
+/// ```
+/// use ccash_rs::blocking::BlockingCCashSession;
+/// use std::time::Duration;
+///
+/// let session = BlockingCCashSession::builder("https://bank.example.com")
+///     .timeout(Duration::from_secs(5))
+///     .user_agent("my-shop-bot/1.0")
+///     .api_prefix("/ccash/api")
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlockingCCashSessionBuilder {
+    base_url: String,
+    api_prefix: String,
+    config: ClientConfig<Client>,
+    retry_policy: RetryPolicy,
+}
+
+impl BlockingCCashSessionBuilder {
+    /// Constructs a new `BlockingCCashSessionBuilder` for the `CCash` instance
+    /// at `base_url`.
+    #[must_use]
+    pub fn new(base_url: &str) -> BlockingCCashSessionBuilder {
+        Self {
+            base_url: base_url.into(),
+            api_prefix: "/api".into(),
+            config: ClientConfig::default(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets the timeout of every request, from when it is sent until the
+    /// response body has been received.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> BlockingCCashSessionBuilder {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for connecting to the `CCash` instance.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> BlockingCCashSessionBuilder {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Adds a proxy that requests are sent through.
+    #[must_use]
+    pub fn proxy(mut self, proxy: Proxy) -> BlockingCCashSessionBuilder {
+        self.config.proxies.push(proxy);
+        self
+    }
+
+    /// Adds a trusted root certificate, such as the certificate of an instance
+    /// that uses a self-signed certificate.
+    #[must_use]
+    pub fn add_root_certificate(
+        mut self,
+        certificate: Certificate,
+    ) -> BlockingCCashSessionBuilder {
+        self.config.root_certificates.push(certificate);
+        self
+    }
+
+    /// Sets whether invalid certificates are accepted. This is dangerous, as
+    /// any certificate will be trusted, and should only be used for testing.
+    #[must_use]
+    pub fn danger_accept_invalid_certs(
+        mut self,
+        accept: bool,
+    ) -> BlockingCCashSessionBuilder {
+        self.config.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: &str) -> BlockingCCashSessionBuilder {
+        self.config.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Sets the [`Client`] used to make requests. When set, the timeout, proxy,
+    /// certificate and user agent options of this builder are ignored, as the
+    /// `client` is used as-is.
+    #[must_use]
+    pub fn client(mut self, client: Client) -> BlockingCCashSessionBuilder {
+        self.config.client = Some(client);
+        self
+    }
+
+    /// Sets the path of the API relative to the base URL. Defaults to `/api`,
+    /// but can be changed for instances behind a reverse proxy.
+    #[must_use]
+    pub fn api_prefix(mut self, api_prefix: &str) -> BlockingCCashSessionBuilder {
+        self.api_prefix = api_prefix.into();
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] used for requests made with the
+    /// `BlockingCCashSession`.
+    #[must_use]
+    pub fn retry_policy(
+        mut self,
+        retry_policy: RetryPolicy,
+    ) -> BlockingCCashSessionBuilder {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Builds the `BlockingCCashSession`. The client is only built once
+    /// [`establish_connection`](BlockingCCashSession::establish_connection) is
+    /// called.
+    #[must_use]
+    pub fn build(self) -> BlockingCCashSession {
+        BlockingCCashSession {
+            session_url: api_url(&self.base_url, &self.api_prefix),
+            connection: Connection::new(),
+            client_config: self.config,
+            retry_policy: self.retry_policy,
+        }
+    }
+}
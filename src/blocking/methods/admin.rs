@@ -0,0 +1,276 @@
+//! This module contains the blocking versions of all the admin functions found
+//! in [`crate::methods::admin`]. Blocking non-admin functions can be found
+//! within [`methods`].
+//!
+//! [`methods`]: crate::blocking::methods
+
+use crate::{
+    blocking::BlockingCCashSession,
+    request::{blocking_request, Endpoint},
+    user::Credentials,
+    CCashError, CCashResponse, CCashUser, Csh, Result, Username,
+};
+use velcro::hash_map;
+
+/// Returns a boolean whether or not the [`user`](CCashUser) is an admin
+/// account. Blocking version of [`crate::methods::admin::verify_account`].
+///
+/// # Errors
+///
+/// See [`crate::methods::admin::verify_account`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn verify_account(session: &BlockingCCashSession, user: &CCashUser) -> Result<bool> {
+    let endpoint = Endpoint::AdminVerifyAccount;
+
+    let r = blocking_request::<()>(session, endpoint, Some(user), None, &[])?;
+    r.into_bool(endpoint, Some(401))
+}
+
+/// Changes the password for the [`user`](CCashUser), updating `user` to use
+/// `new_password`. Blocking version of
+/// [`crate::methods::admin::change_password`].
+///
+/// # Errors
+///
+/// See [`crate::methods::admin::change_password`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, user = %user.username)
+    )
+)]
+pub fn change_password(
+    session: &BlockingCCashSession,
+    admin_user: &CCashUser,
+    user: &mut CCashUser,
+    new_password: &str,
+) -> Result<bool> {
+    let endpoint = Endpoint::AdminChangePassword;
+
+    let new_user = CCashUser::new(&user.username.clone(), new_password)?;
+
+    let r = blocking_request(
+        session,
+        endpoint,
+        Some(admin_user),
+        Some(&new_user.credentials()),
+        &[],
+    )?;
+    let changed = r.into_bool(endpoint, None)?;
+    if changed {
+        *user = new_user;
+    }
+
+    Ok(changed)
+}
+
+/// Sets the balance of a user with the given `username` to the amount described
+/// by `new_balance`. Blocking version of
+/// [`crate::methods::admin::set_balance`].
+///
+/// # Errors
+///
+/// See [`crate::methods::admin::set_balance`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, username = %username)
+    )
+)]
+pub fn set_balance(
+    session: &BlockingCCashSession,
+    admin_user: &CCashUser,
+    username: &Username,
+    new_balance: impl Into<Csh>,
+) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct SetBalanceData {
+        name: String,
+        amount: Csh,
+    }
+
+    let endpoint = Endpoint::AdminSetBalance;
+
+    let body = SetBalanceData {
+        name: username.to_string(),
+        amount: new_balance.into(),
+    };
+
+    let r = blocking_request(session, endpoint, Some(admin_user), Some(&body), &[])?;
+    r.into_unit(endpoint)
+}
+
+/// Impacts the balance of user with the given `username` by the amount
+/// described by `amount`. Blocking version of
+/// [`crate::methods::admin::impact_balance`].
+///
+/// # Errors
+///
+/// See [`crate::methods::admin::impact_balance`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, username = %username)
+    )
+)]
+pub fn impact_balance(
+    session: &BlockingCCashSession,
+    admin_user: &CCashUser,
+    username: &Username,
+    amount: i64,
+) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct ImpactBalanceData {
+        name: String,
+        amount: i64,
+    }
+
+    let endpoint = Endpoint::AdminImpactBalance;
+
+    let body = ImpactBalanceData {
+        name: username.to_string(),
+        amount,
+    };
+
+    let r = blocking_request(session, endpoint, Some(admin_user), Some(&body), &[])?;
+    r.into_unit(endpoint)
+}
+
+/// Adds a [`user`](CCashUser) with the balance determined by `amount`.
+/// Blocking version of [`crate::methods::admin::add_user`].
+///
+/// # Errors
+///
+/// See [`crate::methods::admin::add_user`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, new_user = %new_user.username)
+    )
+)]
+pub fn add_user(
+    session: &BlockingCCashSession,
+    admin_user: &CCashUser,
+    new_user: &CCashUser,
+    amount: impl Into<Csh>,
+) -> Result<bool> {
+    #[derive(serde::Serialize)]
+    struct AddUserData<'a> {
+        #[serde(flatten)]
+        user: Credentials<'a>,
+        amount: Csh,
+    }
+
+    let endpoint = Endpoint::AdminAddUser;
+
+    let body = AddUserData {
+        user: new_user.credentials(),
+        amount: amount.into(),
+    };
+
+    let r = blocking_request(session, endpoint, Some(admin_user), Some(&body), &[])?;
+    r.into_bool(endpoint, Some(409))
+}
+
+/// Removes a user associated with the `username`. Blocking version of
+/// [`crate::methods::admin::delete_user`].
+///
+/// # Errors
+///
+/// See [`crate::methods::admin::delete_user`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, username = %username)
+    )
+)]
+pub fn delete_user(
+    session: &BlockingCCashSession,
+    admin_user: &CCashUser,
+    username: &Username,
+) -> Result<()> {
+    let endpoint = Endpoint::AdminDeleteUser;
+    let body = hash_map! { "name": username.as_str() };
+
+    let r = blocking_request(session, endpoint, Some(admin_user), Some(&body), &[])?;
+    r.into_unit(endpoint)
+}
+
+/// Prunes users with less than `amount` in balance or users with transactions
+/// older than `time` and less than `amount` in balance. Blocking version of
+/// [`crate::methods::admin::prune_users`].
+///
+/// # Errors
+///
+/// See [`crate::methods::admin::prune_users`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(admin = %admin_user.username))
+)]
+pub fn prune_users(
+    session: &BlockingCCashSession,
+    admin_user: &CCashUser,
+    amount: impl Into<Csh>,
+    time: Option<i64>,
+) -> Result<u64> {
+    #[derive(serde::Serialize)]
+    struct PruneUsersData {
+        amount: Csh,
+        time: Option<i64>,
+    }
+
+    let endpoint = Endpoint::AdminPruneUsers;
+
+    let body = PruneUsersData {
+        amount: amount.into(),
+        time,
+    };
+
+    let r = blocking_request(session, endpoint, Some(admin_user), Some(&body), &[])?;
+    match r {
+        CCashResponse::Success { .. } =>
+            if let Ok(amount) = r.convert_message::<u64>() {
+                Ok(amount)
+            } else {
+                Err(CCashError::Error(
+                    "Could not parse amount of users pruned into a valid u64".into(),
+                ))
+            },
+        CCashResponse::Error { .. } => Err(r.into_error(endpoint)),
+    }
+}
+
+/// Saves and closes the `CCash` instance, updating
+/// [`session`](BlockingCCashSession) to reflect that the connection has
+/// closed. Blocking version of [`crate::methods::admin::close`].
+///
+/// # Errors
+///
+/// See [`crate::methods::admin::close`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(admin = %admin_user.username))
+)]
+pub fn close(session: &mut BlockingCCashSession, admin_user: &CCashUser) -> Result<()> {
+    let endpoint = Endpoint::AdminClose;
+
+    let r = blocking_request::<()>(session, endpoint, Some(admin_user), None, &[])?;
+    r.into_unit(endpoint)?;
+
+    session.connection.write().clear();
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+//! This module contains the blocking versions of all the non-admin functions
+//! found in [`crate::methods`]. All blocking admin functions can be found
+//! within the [`admin`] module.
+
+pub mod admin;
+
+use crate::{
+    blocking::BlockingCCashSession,
+    history::BalanceHistory,
+    methods::BALANCE_HISTORY_ATTEMPTS,
+    query::LogQuery,
+    request::{blocking_request, Endpoint},
+    CCashApiError, CCashError, CCashSessionProperties, CCashUser, Capability, Csh,
+    Result, TransactionLog, TransactionLogV2,
+};
+use velcro::hash_map;
+
+/// Returns the balance of the [`user`](CCashUser). Blocking version of
+/// [`crate::methods::get_balance`].
+///
+/// # Errors
+///
+/// See [`crate::methods::get_balance`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn get_balance(session: &BlockingCCashSession, user: &CCashUser) -> Result<Csh> {
+    let endpoint = Endpoint::GetBalance;
+
+    let r = blocking_request::<()>(
+        session,
+        endpoint,
+        Some(user),
+        None,
+        &[("name", &user.username)],
+    )?;
+    r.into_value(endpoint)
+}
+
+/// Returns the transaction logs for a given [`user`](CCashUser). Blocking
+/// version of [`crate::methods::get_log`].
+///
+/// # Errors
+///
+/// See [`crate::methods::get_log`].
+#[deprecated(since = "2.0.0", note = "Please use `get_log_v2` where possible")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn get_log(
+    session: &BlockingCCashSession,
+    user: &CCashUser,
+) -> Result<Vec<TransactionLog>> {
+    let endpoint = Endpoint::GetLog;
+
+    let r = blocking_request::<()>(session, endpoint, Some(user), None, &[])?;
+    r.into_value(endpoint)
+}
+
+/// Returns the transaction logs for a given [`user`](CCashUser). Blocking
+/// version of [`crate::methods::get_log_v2`].
+///
+/// # Errors
+///
+/// See [`crate::methods::get_log_v2`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn get_log_v2(
+    session: &BlockingCCashSession,
+    user: &CCashUser,
+) -> Result<Vec<TransactionLogV2>> {
+    let endpoint = Endpoint::GetLogV2;
+
+    let r = blocking_request::<()>(session, endpoint, Some(user), None, &[])?;
+    r.into_value(endpoint)
+}
+
+/// Returns the transaction logs for a given [`user`](CCashUser) from the best
+/// endpoint supported by the `CCash` instance. Blocking version of
+/// [`crate::methods::get_logs`].
+///
+/// # Errors
+///
+/// See [`crate::methods::get_logs`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn get_logs(
+    session: &BlockingCCashSession,
+    user: &CCashUser,
+) -> Result<Vec<TransactionLogV2>> {
+    if session.supports(Capability::LogV2) {
+        match get_log_v2(session, user) {
+            // Instances that don't report their version may be older than
+            // version 2, in which case the v2 endpoint is not found.
+            Err(CCashError::ApiError(CCashApiError::UserNotFound { .. }))
+                if session
+                    .get_properties()
+                    .is_some_and(|p| p.get_version().is_none()) => {},
+            result => return result,
+        }
+    }
+
+    let logs = get_log(session, user)?;
+    Ok(logs.iter().map(|log| log.to_v2(&user.username)).collect())
+}
+
+/// Returns the transaction logs for a given [`user`](CCashUser) that match the
+/// `query`. Blocking version of [`crate::methods::get_log_v2_filtered`].
+///
+/// # Errors
+///
+/// See [`crate::methods::get_log_v2_filtered`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn get_log_v2_filtered(
+    session: &BlockingCCashSession,
+    user: &CCashUser,
+    query: &LogQuery,
+) -> Result<Vec<TransactionLogV2>> {
+    let logs = get_logs(session, user)?;
+    Ok(query.apply(logs))
+}
+
+/// Returns the [`BalanceHistory`] of a given [`user`](CCashUser). Blocking
+/// version of [`crate::methods::get_balance_history`].
+///
+/// # Errors
+///
+/// See [`crate::methods::get_balance_history`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn get_balance_history(
+    session: &BlockingCCashSession,
+    user: &CCashUser,
+) -> Result<BalanceHistory> {
+    let max_log = session
+        .get_properties()
+        .as_ref()
+        .map(CCashSessionProperties::get_max_log);
+
+    let mut balance = get_balance(session, user)?;
+    let mut attempt = 1;
+    loop {
+        let logs = get_logs(session, user)?;
+        let balance_after = get_balance(session, user)?;
+
+        if balance_after == balance || attempt >= BALANCE_HISTORY_ATTEMPTS {
+            let mut history = BalanceHistory::from_logs(balance_after, logs, max_log);
+            history.is_consistent = balance_after == balance;
+            return Ok(history);
+        }
+
+        balance = balance_after;
+        attempt += 1;
+    }
+}
+
+/// Returns a `bool` about whether or not the the user with a given
+/// [`user`](CCashUser) exists. Blocking version of
+/// [`crate::methods::contains_user`].
+///
+/// # Errors
+///
+/// See [`crate::methods::contains_user`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn contains_user(session: &BlockingCCashSession, user: &CCashUser) -> Result<bool> {
+    let endpoint = Endpoint::ContainsUser;
+
+    let r = blocking_request::<()>(
+        session,
+        endpoint,
+        Some(user),
+        None,
+        &[("name", &user.username)],
+    )?;
+    r.into_bool(endpoint, Some(401))
+}
+
+/// Returns a `bool` about whether or not the `password` for a given
+/// [`user`](CCashUser) is correct. Blocking version of
+/// [`crate::methods::verify_password`].
+///
+/// # Errors
+///
+/// See [`crate::methods::verify_password`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn verify_password(session: &BlockingCCashSession, user: &CCashUser) -> Result<bool> {
+    let endpoint = Endpoint::VerifyPassword;
+
+    let r = blocking_request::<()>(session, endpoint, Some(user), None, &[])?;
+    r.into_bool(endpoint, Some(401))
+}
+
+/// Returns `true` about if a password change was successful for the given
+/// [`user`](CCashUser), updating `user` to use `new_password`. Blocking
+/// version of [`crate::methods::change_password`].
+///
+/// # Errors
+///
+/// See [`crate::methods::change_password`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn change_password(
+    session: &BlockingCCashSession,
+    user: &mut CCashUser,
+    new_password: &str,
+) -> Result<bool> {
+    let endpoint = Endpoint::ChangePassword;
+    let body = hash_map! { "pass": new_password };
+
+    let r = blocking_request(session, endpoint, Some(user), Some(&body), &[])?;
+    let changed = r.into_bool(endpoint, None)?;
+    if changed {
+        user.update_password(new_password);
+    }
+
+    Ok(changed)
+}
+
+/// Sends funds from the [`user`](CCashUser) to the user with the
+/// `recipient_name`, returning the [`user`](CCashUser)'s new balance. Blocking
+/// version of [`crate::methods::send_funds`].
+///
+/// # Errors
+///
+/// See [`crate::methods::send_funds`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(user = %user.username, recipient = recipient_name)
+    )
+)]
+pub fn send_funds(
+    session: &BlockingCCashSession,
+    user: &CCashUser,
+    recipient_name: &str,
+    amount: impl Into<Csh>,
+) -> Result<Csh> {
+    #[derive(serde::Serialize)]
+    struct FundsTransfer {
+        name: String,
+        amount: Csh,
+    }
+
+    let endpoint = Endpoint::SendFunds;
+    let body = FundsTransfer {
+        name: recipient_name.into(),
+        amount: amount.into(),
+    };
+
+    let r = blocking_request(session, endpoint, Some(user), Some(&body), &[])?;
+    r.into_value(endpoint)
+}
+
+/// Adds a [`user`](CCashUser) with a balance of 0. Blocking version of
+/// [`crate::methods::add_user`].
+///
+/// # Errors
+///
+/// See [`crate::methods::add_user`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn add_user(session: &BlockingCCashSession, user: &CCashUser) -> Result<bool> {
+    let endpoint = Endpoint::AddUser;
+
+    let r = blocking_request(session, endpoint, None, Some(&user.credentials()), &[])?;
+    r.into_bool(endpoint, Some(409))
+}
+
+/// Removes the [`user`](CCashUser). Blocking version of
+/// [`crate::methods::delete_user`].
+///
+/// # Errors
+///
+/// See [`crate::methods::delete_user`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub fn delete_user(session: &BlockingCCashSession, user: &CCashUser) -> Result<()> {
+    let endpoint = Endpoint::DeleteUser;
+
+    let r = blocking_request::<()>(session, endpoint, Some(user), None, &[])?;
+    r.into_unit(endpoint)
+}
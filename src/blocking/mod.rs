@@ -0,0 +1,238 @@
+//! This module contains a blocking (synchronous) version of the API, for use
+//! where an asynchronous runtime is not available. It requires the `blocking`
+//! feature to be enabled.
+//!
+//! [`BlockingCCashSession`], [`BlockingCCashSessionBuilder`] and the functions
+//! in [`methods`] and [`methods::admin`] mirror
+//! [`CCashSession`](crate::CCashSession),
+//! [`CCashSessionBuilder`](crate::CCashSessionBuilder) and the functions in
+//! [`crate::methods`], and interpret the responses of `CCash` in exactly the
+//! same way.
+
+pub mod builder;
+pub mod methods;
+
+pub use builder::BlockingCCashSessionBuilder;
+
+use crate::{
+    builder::ClientConfig, connection::Connection, CCashError, CCashSessionProperties,
+    Capability, Result, RetryPolicy, MAX_RECONNECT_DELAY,
+};
+use reqwest::blocking::Client;
+use std::time::{Duration, Instant};
+
+/// Struct that describes the blocking connection to the `CCash` API instance
+/// which is defined by the `session_url`.
+///
+/// Before any function from [`methods`] and [`methods::admin`] is called,
+/// [`establish_connection`](BlockingCCashSession::establish_connection) must
+/// be called to make sure that the connection to the `CCash` instance is
+/// secured and correct.
+///
+/// # Panics
+///
+/// Like [`reqwest::blocking`], this session must not be used from within an
+/// asynchronous runtime.
+#[derive(Debug, Clone)]
+pub struct BlockingCCashSession {
+    pub(crate) session_url: String,
+    pub(crate) connection: Connection<Client>,
+    pub(crate) client_config: ClientConfig<Client>,
+    pub(crate) retry_policy: RetryPolicy,
+}
+
+impl BlockingCCashSession {
+    /// Constructs a new `BlockingCCashSession` from a `base_url`
+    #[must_use]
+    pub fn new(base_url: &str) -> BlockingCCashSession {
+        BlockingCCashSessionBuilder::new(base_url).build()
+    }
+
+    /// Returns a [`BlockingCCashSessionBuilder`] for the `CCash` instance at
+    /// `base_url`, to configure timeouts, proxies, certificates, the user
+    /// agent, or the client used by the `BlockingCCashSession`.
+    #[must_use]
+    pub fn builder(base_url: &str) -> BlockingCCashSessionBuilder {
+        BlockingCCashSessionBuilder::new(base_url)
+    }
+
+    /// Sets the [`RetryPolicy`] used for requests made with this
+    /// `BlockingCCashSession`. By default, requests are never retried, but
+    /// the connection is re-established when they cannot reach the instance.
+    #[must_use]
+    pub fn with_retry_policy(
+        mut self,
+        retry_policy: RetryPolicy,
+    ) -> BlockingCCashSession {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] used for requests made with this
+    /// `BlockingCCashSession`.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Establishes a connection to the `CCash` instance using the
+    /// `session_url`.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`CCashError::CouldNotParsePropertiesResponse`] if the
+    /// properties returned by `CCash` could not be parsed correctly.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "info",
+            skip_all,
+            fields(url = %self.session_url),
+            err(Display)
+        )
+    )]
+    pub fn establish_connection(&mut self) -> Result<()> {
+        if self.is_connected() {
+            return Ok(());
+        }
+
+        let client = self.client_config.build()?;
+        let properties = self.fetch_properties(&client)?;
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            version = properties.version,
+            max_log = properties.max_log,
+            "connected to CCash instance"
+        );
+        self.connection.write().connect(client, properties);
+        Ok(())
+    }
+
+    /// Checks that the `CCash` instance is available by requesting its
+    /// properties, returning the time it took to respond. Blocking version of
+    /// [`CCashSession::ping`](crate::CCashSession::ping).
+    ///
+    /// # Errors
+    ///
+    /// See [`CCashSession::ping`](crate::CCashSession::ping).
+    pub fn ping(&self) -> Result<Duration> {
+        let client = self.client_or_build()?;
+        let start = Instant::now();
+        self.fetch_properties(&client)?;
+        Ok(start.elapsed())
+    }
+
+    /// Re-establishes the connection to the `CCash` instance, refreshing its
+    /// properties. Blocking version of
+    /// [`CCashSession::reconnect`](crate::CCashSession::reconnect).
+    ///
+    /// # Errors
+    ///
+    /// See [`CCashSession::reconnect`](crate::CCashSession::reconnect).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "info",
+            skip_all,
+            fields(url = %self.session_url),
+            err(Display, level = "warn")
+        )
+    )]
+    pub fn reconnect(&mut self) -> Result<()> {
+        let client = self.client_or_build()?;
+
+        match self.fetch_properties(&client) {
+            Ok(properties) => {
+                self.connection.write().connect(client, properties);
+                Ok(())
+            },
+            Err(e) => {
+                self.connection.write().is_connected = false;
+                Err(e)
+            },
+        }
+    }
+
+    /// Waits until the `CCash` instance is available and re-establishes the
+    /// connection to it, trying again with an increasing delay for up to
+    /// `timeout`. Blocking version of
+    /// [`CCashSession::wait_until_available`](crate::CCashSession::wait_until_available).
+    ///
+    /// # Errors
+    ///
+    /// Will return the last [`CCashError`] returned by
+    /// [`reconnect`](Self::reconnect) if the `CCash` instance did not become
+    /// available within `timeout`.
+    pub fn wait_until_available(&mut self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut delay = Duration::from_millis(250);
+
+        loop {
+            let error = match self.reconnect() {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(error);
+            }
+
+            std::thread::sleep(delay.min(remaining));
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Fetches the properties of the `CCash` instance with the `client`.
+    pub(crate) fn fetch_properties(
+        &self,
+        client: &Client,
+    ) -> Result<CCashSessionProperties> {
+        let request = client
+            .get(format!("{}/properties", self.session_url))
+            .header("Accept", "application/json")
+            .build()?;
+
+        let response = client.execute(request)?;
+
+        response
+            .json::<CCashSessionProperties>()
+            .map_err(|_| CCashError::CouldNotParsePropertiesResponse)
+    }
+
+    fn client_or_build(&self) -> Result<Client> {
+        match self.get_client() {
+            Some(client) => Ok(client),
+            None => Ok(self.client_config.build()?),
+        }
+    }
+
+    /// Gets the client associated with this instance of
+    /// `BlockingCCashSession`
+    #[must_use]
+    pub fn get_client(&self) -> Option<Client> { self.connection.read().client.clone() }
+    /// Returns whether or not the `BlockingCCashSession` is connectd to the
+    /// instance.
+    #[must_use]
+    pub fn is_connected(&self) -> bool { self.connection.read().is_connected }
+    /// Returns the properties of the `CCash` instance. These are refreshed
+    /// whenever the connection is re-established.
+    #[must_use]
+    pub fn get_properties(&self) -> Option<CCashSessionProperties> {
+        self.connection.read().properties.clone()
+    }
+    /// Returns whether the connected `CCash` instance supports the
+    /// `capability`, or `false` if the connection has not been established.
+    #[must_use]
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.connection
+            .read()
+            .properties
+            .as_ref()
+            .is_some_and(|p| p.supports(capability))
+    }
+    /// Returns the [`RetryPolicy`] used for requests made with this
+    /// `BlockingCCashSession`.
+    #[must_use]
+    pub fn get_retry_policy(&self) -> &RetryPolicy { &self.retry_policy }
+}
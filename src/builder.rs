@@ -0,0 +1,207 @@
+//! This module contains the [`CCashSessionBuilder`], used to configure a
+//! [`CCashSession`] beyond its base URL.
+
+use crate::{api_url, connection::Connection, CCashSession, RetryPolicy};
+use reqwest::{Certificate, Client, Proxy};
+use std::time::Duration;
+
+/// The options used to build the client of a [`CCashSession`] when
+/// [`establish_connection`](CCashSession::establish_connection) is called,
+/// where `C` is either the async or the blocking [`reqwest`] client.
+#[derive(Debug, Clone)]
+pub(crate) struct ClientConfig<C = Client> {
+    pub(crate) client: Option<C>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) proxies: Vec<Proxy>,
+    pub(crate) root_certificates: Vec<Certificate>,
+    pub(crate) accept_invalid_certs: bool,
+    pub(crate) user_agent: Option<String>,
+}
+
+impl<C> Default for ClientConfig<C> {
+    fn default() -> Self {
+        Self {
+            client: None,
+            timeout: None,
+            connect_timeout: None,
+            proxies: Vec::new(),
+            root_certificates: Vec::new(),
+            accept_invalid_certs: false,
+            user_agent: None,
+        }
+    }
+}
+
+/// Returns the injected client of the `config` if there is one, otherwise
+/// builds a new client with its options from the client `builder`, which has
+/// the same methods for the async and the blocking client.
+macro_rules! build_client {
+    ($config:expr, $builder:expr) => {{
+        let config = $config;
+        match &config.client {
+            Some(client) => Ok(client.clone()),
+            None => {
+                let mut builder = $builder;
+
+                if let Some(timeout) = config.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(timeout) = config.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                for proxy in &config.proxies {
+                    builder = builder.proxy(proxy.clone());
+                }
+                for certificate in &config.root_certificates {
+                    builder = builder.add_root_certificate(certificate.clone());
+                }
+                if config.accept_invalid_certs {
+                    builder = builder.danger_accept_invalid_certs(true);
+                }
+                if let Some(user_agent) = &config.user_agent {
+                    builder = builder.user_agent(user_agent);
+                }
+
+                builder.build()
+            },
+        }
+    }};
+}
+
+impl ClientConfig {
+    /// Returns the injected client if there is one, otherwise builds a new
+    /// client with the configured options.
+    pub(crate) fn build(&self) -> reqwest::Result<Client> {
+        build_client!(self, Client::builder())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl ClientConfig<reqwest::blocking::Client> {
+    /// Blocking version of [`ClientConfig::build`].
+    pub(crate) fn build(&self) -> reqwest::Result<reqwest::blocking::Client> {
+        build_client!(self, reqwest::blocking::Client::builder())
+    }
+}
+
+/// Builder for a [`CCashSession`], for when the defaults used by
+/// [`CCashSession::new`] are not suitable, such as for instances with
+/// self-signed certificates or instances behind a reverse proxy.
+///
+/// ```
+/// use ccash_rs::CCashSession;
+/// use std::time::Duration;
+///
+/// let session = CCashSession::builder("https://bank.example.com")
+///     .timeout(Duration::from_secs(5))
+///     .user_agent("my-shop-bot/1.0")
+///     .api_prefix("/ccash/api")
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CCashSessionBuilder {
+    base_url: String,
+    api_prefix: String,
+    config: ClientConfig,
+    retry_policy: RetryPolicy,
+}
+
+impl CCashSessionBuilder {
+    /// Constructs a new `CCashSessionBuilder` for the `CCash` instance at
+    /// `base_url`.
+    #[must_use]
+    pub fn new(base_url: &str) -> CCashSessionBuilder {
+        Self {
+            base_url: base_url.into(),
+            api_prefix: "/api".into(),
+            config: ClientConfig::default(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets the timeout of every request, from when it is sent until the
+    /// response body has been received.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> CCashSessionBuilder {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for connecting to the `CCash` instance.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> CCashSessionBuilder {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Adds a proxy that requests are sent through.
+    #[must_use]
+    pub fn proxy(mut self, proxy: Proxy) -> CCashSessionBuilder {
+        self.config.proxies.push(proxy);
+        self
+    }
+
+    /// Adds a trusted root certificate, such as the certificate of an instance
+    /// that uses a self-signed certificate.
+    #[must_use]
+    pub fn add_root_certificate(
+        mut self,
+        certificate: Certificate,
+    ) -> CCashSessionBuilder {
+        self.config.root_certificates.push(certificate);
+        self
+    }
+
+    /// Sets whether invalid certificates are accepted. This is dangerous, as
+    /// any certificate will be trusted, and should only be used for testing.
+    #[must_use]
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> CCashSessionBuilder {
+        self.config.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: &str) -> CCashSessionBuilder {
+        self.config.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Sets the [`Client`] used to make requests. When set, the timeout, proxy,
+    /// certificate and user agent options of this builder are ignored, as the
+    /// `client` is used as-is.
+    #[must_use]
+    pub fn client(mut self, client: Client) -> CCashSessionBuilder {
+        self.config.client = Some(client);
+        self
+    }
+
+    /// Sets the path of the API relative to the base URL. Defaults to `/api`,
+    /// but can be changed for instances behind a reverse proxy.
+    #[must_use]
+    pub fn api_prefix(mut self, api_prefix: &str) -> CCashSessionBuilder {
+        self.api_prefix = api_prefix.into();
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] used for requests made with the
+    /// `CCashSession`.
+    #[must_use]
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> CCashSessionBuilder {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Builds the `CCashSession`. The client is only built once
+    /// [`establish_connection`](CCashSession::establish_connection) is called.
+    #[must_use]
+    pub fn build(self) -> CCashSession {
+        CCashSession {
+            session_url: api_url(&self.base_url, &self.api_prefix),
+            connection: Connection::new(),
+            client_config: self.config,
+            retry_policy: self.retry_policy,
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! This module contains functions to run admin operations on many users at
+//! once, such as creating the accounts for a new season from a CSV file with
+//! [`provision_users`]. Event rewards can be paid out to many users at once
+//! with [`pay_out`](crate::payout::pay_out).
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use ccash_rs::{bulk::*, *};
+//! use std::fs::File;
+//!
+//! let mut session = CCashSession::new("https://bank.example.com");
+//! session.establish_connection().await?;
+//! let admin = CCashUser::new("admin", "password")?;
+//!
+//! let options = ProvisionOptions::new().dry_run(true);
+//! let report =
+//!     provision_users(&session, &admin, File::open("season.csv")?, &options).await?;
+//! println!(
+//!     "{} users would be created",
+//!     report.count(ProvisionStatus::WouldCreate)
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    methods, payout::user_exists, CCashError, CCashSession, CCashUser, Csh,
+    UsernamePolicy,
+};
+use futures::{stream, StreamExt};
+use std::{collections::HashSet, io::Read};
+use thiserror::Error;
+
+/// Enum for the errors that stop a bulk operation before it makes any
+/// requests. Errors with single rows are reported per row instead.
+#[derive(Error, Debug)]
+pub enum BulkError {
+    /// An error when reading the CSV.
+    #[error("Could not read CSV: {0}")]
+    Csv(#[from] csv::Error),
+    /// An error when the CSV header is missing a required column.
+    #[error("The CSV header is missing the \"{0}\" column")]
+    MissingColumn(&'static str),
+}
+
+/// Builder that describes how [`provision_users`] runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvisionOptions {
+    concurrency: usize,
+    dry_run: bool,
+    username_policy: UsernamePolicy,
+}
+
+impl Default for ProvisionOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            dry_run: false,
+            username_policy: UsernamePolicy::default(),
+        }
+    }
+}
+
+impl ProvisionOptions {
+    /// Constructs new `ProvisionOptions` that make at most 8 requests at a
+    /// time.
+    #[must_use]
+    pub fn new() -> ProvisionOptions { Self::default() }
+
+    /// Sets the max amount of requests made at a time.
+    #[must_use]
+    pub fn concurrency(mut self, concurrency: usize) -> ProvisionOptions {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets whether this is a dry run. A dry run validates every row and
+    /// checks which users already exist, without creating any users.
+    #[must_use]
+    pub fn dry_run(mut self, dry_run: bool) -> ProvisionOptions {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sets the [`UsernamePolicy`] that the username of every row is checked
+    /// against.
+    #[must_use]
+    pub fn username_policy(mut self, policy: UsernamePolicy) -> ProvisionOptions {
+        self.username_policy = policy;
+        self
+    }
+}
+
+/// Enum for the status of a row processed by [`provision_users`], without any
+/// details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProvisionStatus {
+    /// See [`ProvisionOutcome::Created`].
+    Created,
+    /// See [`ProvisionOutcome::WouldCreate`].
+    WouldCreate,
+    /// See [`ProvisionOutcome::AlreadyExists`].
+    AlreadyExists,
+    /// See [`ProvisionOutcome::Invalid`].
+    Invalid,
+    /// See [`ProvisionOutcome::Failed`].
+    Failed,
+}
+
+/// Enum for the outcome of a row processed by [`provision_users`].
+#[derive(Debug)]
+pub enum ProvisionOutcome {
+    /// The user was created.
+    Created,
+    /// The user would have been created, if this was not a dry run.
+    WouldCreate,
+    /// A user with the same name already exists, so it was left as-is.
+    AlreadyExists,
+    /// The row could not be used, such as when the username is invalid or the
+    /// balance is not a number. No request was made for it.
+    Invalid(String),
+    /// The request to create the user failed.
+    Failed(CCashError),
+}
+
+impl ProvisionOutcome {
+    /// Returns the [`ProvisionStatus`] of this outcome.
+    #[must_use]
+    pub fn status(&self) -> ProvisionStatus {
+        match self {
+            ProvisionOutcome::Created => ProvisionStatus::Created,
+            ProvisionOutcome::WouldCreate => ProvisionStatus::WouldCreate,
+            ProvisionOutcome::AlreadyExists => ProvisionStatus::AlreadyExists,
+            ProvisionOutcome::Invalid(_) => ProvisionStatus::Invalid,
+            ProvisionOutcome::Failed(_) => ProvisionStatus::Failed,
+        }
+    }
+}
+
+/// The outcome of a single row processed by [`provision_users`].
+#[derive(Debug)]
+pub struct ProvisionResult {
+    pub(crate) line: u64,
+    pub(crate) username: String,
+    pub(crate) balance: Option<Csh>,
+    pub(crate) outcome: ProvisionOutcome,
+}
+
+impl ProvisionResult {
+    /// Returns the line of the row in the CSV, starting at 1 for the header.
+    #[must_use]
+    pub fn get_line(&self) -> u64 { self.line }
+
+    /// Returns the username in the row, as written in the CSV.
+    #[must_use]
+    pub fn get_username(&self) -> &str { &self.username }
+
+    /// Returns the starting balance in the row, if it could be parsed.
+    #[must_use]
+    pub fn get_balance(&self) -> Option<Csh> { self.balance }
+
+    /// Returns the outcome of the row.
+    #[must_use]
+    pub fn get_outcome(&self) -> &ProvisionOutcome { &self.outcome }
+}
+
+/// The report returned by [`provision_users`], with a result for every row in
+/// the CSV, in the order of the CSV.
+#[derive(Debug, Default)]
+pub struct ProvisionReport {
+    pub(crate) results: Vec<ProvisionResult>,
+}
+
+impl ProvisionReport {
+    /// Returns the results of every row, in the order of the CSV.
+    #[must_use]
+    pub fn get_results(&self) -> &[ProvisionResult] { &self.results }
+
+    /// Returns the amount of rows with the given `status`.
+    #[must_use]
+    pub fn count(&self, status: ProvisionStatus) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.outcome.status() == status)
+            .count()
+    }
+
+    /// Returns whether every row was created, would be created, or already
+    /// existed.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.count(ProvisionStatus::Invalid) == 0
+            && self.count(ProvisionStatus::Failed) == 0
+    }
+}
+
+/// Creates a user for every row of the CSV read from `reader` with
+/// [`admin::add_user`](methods::admin::add_user), making at most
+/// [`concurrency`](ProvisionOptions::concurrency) requests at a time.
+///
+/// The CSV must have a header with a `username` and a `password` column, and
+/// may have a `balance` column with the starting balance of each user, which
+/// defaults to 0 and is parsed as a [`Csh`], so amounts like `1.2k` are
+/// accepted. The username of each row is checked against the
+/// [`UsernamePolicy`] of the `options` before any request is made, and rows
+/// with invalid or repeated usernames are reported
+/// as [`ProvisionOutcome::Invalid`].
+///
+/// # Errors
+///
+/// Will return a [`BulkError`] if the CSV cannot be read or is missing a
+/// column. Errors with single rows, including failed requests, are reported in
+/// the [`ProvisionReport`].
+pub async fn provision_users<R: Read>(
+    session: &CCashSession,
+    admin_user: &CCashUser,
+    reader: R,
+    options: &ProvisionOptions,
+) -> Result<ProvisionReport, BulkError> {
+    let (mut results, pending) = read_provision_rows(reader, &options.username_policy)?;
+
+    let created = stream::iter(pending)
+        .map(|(mut result, user, balance)| async move {
+            result.outcome = if options.dry_run {
+                match user_exists(session, &user).await {
+                    Ok(true) => ProvisionOutcome::AlreadyExists,
+                    Ok(false) => ProvisionOutcome::WouldCreate,
+                    Err(e) => ProvisionOutcome::Failed(e),
+                }
+            } else {
+                match methods::admin::add_user(session, admin_user, &user, balance).await
+                {
+                    Ok(true) => ProvisionOutcome::Created,
+                    Ok(false) => ProvisionOutcome::AlreadyExists,
+                    Err(e) => ProvisionOutcome::Failed(e),
+                }
+            };
+
+            result
+        })
+        .buffer_unordered(options.concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    results.extend(created);
+    results.sort_by_key(|r| r.line);
+
+    Ok(ProvisionReport { results })
+}
+
+/// A validated row that is ready to be provisioned.
+type PendingRow = (ProvisionResult, CCashUser, Csh);
+
+/// Reads and validates the rows of the CSV read from `reader`, returning the
+/// results of invalid rows and the rows that are ready to be provisioned.
+fn read_provision_rows<R: Read>(
+    reader: R,
+    policy: &UsernamePolicy,
+) -> Result<(Vec<ProvisionResult>, Vec<PendingRow>), BulkError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let headers = reader.headers()?.clone();
+    let column = |name: &'static str| {
+        headers
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(name))
+            .ok_or(BulkError::MissingColumn(name))
+    };
+    let username_column = column("username")?;
+    let password_column = column("password")?;
+    let balance_column = column("balance").ok();
+
+    let mut seen = HashSet::new();
+    let mut results = Vec::new();
+    let mut pending = Vec::new();
+
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, csv::Position::line);
+        let username = record.get(username_column).unwrap_or_default().to_owned();
+        let password = record.get(password_column).unwrap_or_default();
+        let balance = match balance_column.and_then(|c| record.get(c)) {
+            None | Some("") => Ok(Csh::ZERO),
+            Some(balance) => balance
+                .parse::<Csh>()
+                .map_err(|_| format!("Invalid balance: {balance}")),
+        };
+
+        let mut result = ProvisionResult {
+            line,
+            username,
+            balance: balance.as_ref().ok().copied(),
+            outcome: ProvisionOutcome::WouldCreate,
+        };
+
+        let user = policy
+            .validate(&result.username)
+            .map(|username| CCashUser::from_username(username, password))
+            .map_err(|e| e.to_string())
+            .and_then(|user| balance.map(|balance| (user, balance)));
+
+        match user {
+            Err(reason) => result.outcome = ProvisionOutcome::Invalid(reason),
+            Ok((user, _)) if !seen.insert(user.get_username().to_owned()) => {
+                result.outcome =
+                    ProvisionOutcome::Invalid("Username is repeated in the CSV".into());
+            },
+            Ok((user, balance)) => {
+                pending.push((result, user, balance));
+                continue;
+            },
+        }
+
+        results.push(result);
+    }
+
+    Ok((results, pending))
+}
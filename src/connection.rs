@@ -0,0 +1,60 @@
+use crate::CCashSessionProperties;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// The state of the connection of a session to its `CCash` instance, where `C`
+/// is the client used to make requests.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionState<C> {
+    pub(crate) is_connected: bool,
+    pub(crate) client: Option<C>,
+    pub(crate) properties: Option<CCashSessionProperties>,
+}
+
+impl<C> ConnectionState<C> {
+    /// Marks the connection as established with the `client`, and the
+    /// `properties` fetched with it.
+    pub(crate) fn connect(&mut self, client: C, properties: CCashSessionProperties) {
+        self.is_connected = true;
+        self.client = Some(client);
+        self.properties = Some(properties);
+    }
+
+    /// Forgets the client and the properties of the connection, such as after
+    /// the `CCash` instance has been closed.
+    pub(crate) fn clear(&mut self) {
+        self.is_connected = false;
+        self.client = None;
+        self.properties = None;
+    }
+}
+
+/// The connection of a session to its `CCash` instance. It is kept behind a
+/// lock so that a request made through a shared reference to the session can
+/// re-establish it for every later request. Cloning a `Connection` copies its
+/// current state.
+#[derive(Debug)]
+pub(crate) struct Connection<C>(RwLock<ConnectionState<C>>);
+
+impl<C> Connection<C> {
+    pub(crate) fn new() -> Connection<C> {
+        Connection(RwLock::new(ConnectionState {
+            is_connected: false,
+            client: None,
+            properties: None,
+        }))
+    }
+
+    // The state is never left half-updated, so it is still consistent if a
+    // thread panicked while holding the lock.
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, ConnectionState<C>> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn write(&self) -> RwLockWriteGuard<'_, ConnectionState<C>> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<C: Clone> Clone for Connection<C> {
+    fn clone(&self) -> Self { Connection(RwLock::new(self.read().clone())) }
+}
@@ -0,0 +1,520 @@
+//! This module contains functions to export [`TransactionLogV2`] entries to CSV
+//! and NDJSON (JSON Lines), for use in spreadsheets and other tools, and to
+//! import them again from previously exported files. CSV requires the `csv`
+//! feature to be enabled.
+//!
+//! Which columns are written, and how times are formatted, is described by
+//! [`ExportOptions`]. Imports accept files written with any options, as long
+//! as they contain a [`Time`](Column::Time) and a
+//! [`Counterparty`](Column::Counterparty) column, plus either a
+//! [`SignedAmount`](Column::SignedAmount) column or both a
+//! [`Direction`](Column::Direction) and an [`Amount`](Column::Amount) column.
+//! If there is a `Direction` column, it decides the direction of each
+//! transaction.
+//!
+//! ```
+//! use ccash_rs::export::{self, Column, ExportOptions, TimeFormat};
+//! # let logs: Vec<ccash_rs::TransactionLogV2> = vec![];
+//!
+//! let options = ExportOptions::new()
+//!     .columns(&[
+//!         Column::Time,
+//!         Column::Owner,
+//!         Column::Counterparty,
+//!         Column::SignedAmount,
+//!     ])
+//!     .time_format(TimeFormat::Epoch)
+//!     .owner("alice");
+//!
+//! let mut ndjson = Vec::new();
+//! export::write_ndjson(&mut ndjson, &logs, &options)?;
+//! let imported = export::read_ndjson(ndjson.as_slice())?;
+//! # Ok::<(), export::ExportError>(())
+//! ```
+
+use crate::{query::TransactionDirection, Csh, TransactionLogV2};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+};
+use thiserror::Error;
+
+/// Enum for the errors that could occur when exporting or importing logs.
+#[derive(Error, Debug)]
+pub enum ExportError {
+    /// An error when reading from or writing to the underlying reader or
+    /// writer.
+    #[error("I/O error during export: {0}")]
+    Io(#[from] std::io::Error),
+    /// An error when reading or writing CSV.
+    #[cfg(feature = "csv")]
+    #[error("CSV error during export: {0}")]
+    Csv(#[from] csv::Error),
+    /// An error when reading or writing JSON.
+    #[error("JSON error during export: {0}")]
+    Json(#[from] serde_json::Error),
+    /// An error when an imported record is missing a column needed to rebuild
+    /// the log entry.
+    #[error("Record {record} is missing the \"{column}\" column")]
+    MissingColumn {
+        /// The number of the record, starting at 1.
+        record: usize,
+        /// The name of the missing column.
+        column: &'static str,
+    },
+    /// An error when an imported record has a value that could not be parsed.
+    #[error("Record {record} has an invalid value for \"{column}\": {value}")]
+    InvalidValue {
+        /// The number of the record, starting at 1.
+        record: usize,
+        /// The name of the column.
+        column: &'static str,
+        /// The invalid value.
+        value: String,
+    },
+}
+
+/// Enum for the columns that can be exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    /// The time of the transaction, formatted according to the [`TimeFormat`].
+    Time,
+    /// The name of the account that the logs belong to, as set with
+    /// [`ExportOptions::owner`]. Empty if no owner was set.
+    Owner,
+    /// The name of the account where the funds were sent to or received from.
+    Counterparty,
+    /// Either `sent` or `received`.
+    Direction,
+    /// The amount of funds in CSH, always positive.
+    Amount,
+    /// The amount of funds in CSH, negative if the funds were sent. In CSV,
+    /// sending 0 CSH is written as `-0`, so that it is not imported as
+    /// received. NDJSON always writes a number, so the direction of a 0 CSH
+    /// transfer is only kept if the [`Direction`](Column::Direction) column is
+    /// exported as well.
+    SignedAmount,
+}
+
+impl Column {
+    /// Returns the name of the column, as used in CSV headers and NDJSON keys.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Time => "time",
+            Column::Owner => "owner",
+            Column::Counterparty => "counterparty",
+            Column::Direction => "direction",
+            Column::Amount => "amount",
+            Column::SignedAmount => "signed_amount",
+        }
+    }
+}
+
+/// Enum for the formats that times can be exported in. Both are accepted when
+/// importing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeFormat {
+    /// An ISO-8601 (RFC 3339) timestamp in UTC, such as
+    /// `2023-01-01T12:00:00Z`.
+    Iso8601,
+    /// The Unix epoch time in seconds, as returned by `CCash`.
+    Epoch,
+}
+
+/// Builder that describes how logs are exported. By default, the time (in
+/// ISO-8601), counterparty, direction and amount are exported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    columns: Vec<Column>,
+    time_format: TimeFormat,
+    owner: Option<String>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                Column::Time,
+                Column::Counterparty,
+                Column::Direction,
+                Column::Amount,
+            ],
+            time_format: TimeFormat::Iso8601,
+            owner: None,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Constructs new `ExportOptions` with the default columns.
+    #[must_use]
+    pub fn new() -> ExportOptions { Self::default() }
+
+    /// Sets the columns to export, in order.
+    #[must_use]
+    pub fn columns(mut self, columns: &[Column]) -> ExportOptions {
+        self.columns = columns.to_vec();
+        self
+    }
+
+    /// Sets the format that times are exported in.
+    #[must_use]
+    pub fn time_format(mut self, time_format: TimeFormat) -> ExportOptions {
+        self.time_format = time_format;
+        self
+    }
+
+    /// Sets the name of the account that the logs belong to, written in the
+    /// [`Owner`](Column::Owner) column.
+    #[must_use]
+    pub fn owner(mut self, owner: &str) -> ExportOptions {
+        self.owner = Some(owner.to_lowercase());
+        self
+    }
+
+    fn value(&self, column: Column, log: &TransactionLogV2) -> Value {
+        match column {
+            Column::Time => match self.time_format {
+                TimeFormat::Iso8601 => Value::from(
+                    to_datetime(log.time).to_rfc3339_opts(SecondsFormat::Secs, true),
+                ),
+                TimeFormat::Epoch => Value::from(log.time),
+            },
+            Column::Owner => Value::from(self.owner.clone().unwrap_or_default()),
+            Column::Counterparty => Value::from(log.counterparty.clone()),
+            Column::Direction => Value::from(direction_name(log)),
+            Column::Amount => Value::from(log.amount.get()),
+            Column::SignedAmount => Value::from(signed_amount(log)),
+        }
+    }
+
+    #[cfg(feature = "csv")]
+    fn csv_value(&self, column: Column, log: &TransactionLogV2) -> String {
+        if column == Column::SignedAmount && !log.receiving && log.amount.is_zero() {
+            return "-0".into();
+        }
+
+        match self.value(column, log) {
+            Value::String(s) => s,
+            v => v.to_string(),
+        }
+    }
+}
+
+/// A log entry read back from an exported file, along with the owner of the
+/// log if it was exported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedLog {
+    owner: Option<String>,
+    log: TransactionLogV2,
+}
+
+impl ImportedLog {
+    /// Returns the name of the account that the log belongs to, if the file
+    /// had a non-empty [`Owner`](Column::Owner) column.
+    #[must_use]
+    pub fn get_owner(&self) -> Option<&str> { self.owner.as_deref() }
+
+    /// Returns the log entry.
+    #[must_use]
+    pub fn get_log(&self) -> &TransactionLogV2 { &self.log }
+
+    /// Consumes the `ImportedLog` and returns the log entry.
+    #[must_use]
+    pub fn into_log(self) -> TransactionLogV2 { self.log }
+}
+
+/// Writes the `logs` to the `writer` as CSV with a header row, using the
+/// columns and time format of the `options`.
+///
+/// # Errors
+///
+/// Will return an [`ExportError`] if writing to the `writer` fails.
+#[cfg(feature = "csv")]
+pub fn write_csv<'a, W, I>(
+    writer: W,
+    logs: I,
+    options: &ExportOptions,
+) -> Result<(), ExportError>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a TransactionLogV2>,
+{
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(options.columns.iter().map(|c| c.name()))?;
+
+    for log in logs {
+        writer
+            .write_record(options.columns.iter().map(|&c| options.csv_value(c, log)))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the `logs` to the `writer` as NDJSON, one JSON object per line,
+/// using the columns and time format of the `options` as keys.
+///
+/// # Errors
+///
+/// Will return an [`ExportError`] if writing to the `writer` fails.
+pub fn write_ndjson<'a, W, I>(
+    mut writer: W,
+    logs: I,
+    options: &ExportOptions,
+) -> Result<(), ExportError>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a TransactionLogV2>,
+{
+    for log in logs {
+        let object = options
+            .columns
+            .iter()
+            .map(|&c| (c.name().to_owned(), options.value(c, log)))
+            .collect::<Map<_, _>>();
+
+        serde_json::to_writer(&mut writer, &object)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads logs from CSV previously written by [`write_csv`]. Columns are
+/// matched by the names in the header row.
+///
+/// # Errors
+///
+/// Will return an [`ExportError`] if reading from the `reader` fails, if the
+/// CSV is malformed, or if a record is missing a column or has an invalid
+/// value.
+#[cfg(feature = "csv")]
+pub fn read_csv<R: Read>(reader: R) -> Result<Vec<ImportedLog>, ExportError> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let mut imported = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let fields = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(h, v)| (h.to_owned(), v.to_owned()))
+            .collect::<HashMap<_, _>>();
+
+        imported.push(parse_record(index + 1, &fields)?);
+    }
+
+    Ok(imported)
+}
+
+/// Reads logs from NDJSON previously written by [`write_ndjson`]. Blank lines
+/// are skipped.
+///
+/// # Errors
+///
+/// Will return an [`ExportError`] if reading from the `reader` fails, if a
+/// line is not a JSON object, or if a record is missing a column or has an
+/// invalid value.
+pub fn read_ndjson<R: Read>(reader: R) -> Result<Vec<ImportedLog>, ExportError> {
+    let mut imported = Vec::new();
+
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let object: Map<String, Value> = serde_json::from_str(&line)?;
+        let fields = object
+            .into_iter()
+            .map(|(k, v)| match v {
+                Value::String(s) => (k, s),
+                v => (k, v.to_string()),
+            })
+            .collect::<HashMap<_, _>>();
+
+        imported.push(parse_record(imported.len() + 1, &fields)?);
+    }
+
+    Ok(imported)
+}
+
+fn parse_record(
+    record: usize,
+    fields: &HashMap<String, String>,
+) -> Result<ImportedLog, ExportError> {
+    let get = |column: Column| fields.get(column.name()).map(|v| v.trim());
+    let require = |column: Column| {
+        get(column).ok_or(ExportError::MissingColumn {
+            record,
+            column: column.name(),
+        })
+    };
+    let invalid = |column: Column, value: &str| ExportError::InvalidValue {
+        record,
+        column: column.name(),
+        value: value.to_owned(),
+    };
+
+    let time = require(Column::Time)?;
+    let time = time
+        .parse::<i64>()
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(time)
+                .ok()
+                .map(|t| t.timestamp())
+        })
+        .ok_or_else(|| invalid(Column::Time, time))?;
+
+    let direction = match get(Column::Direction) {
+        Some(direction) => match parse_direction(direction) {
+            Some(TransactionDirection::Received) => Some(true),
+            Some(TransactionDirection::Sent) => Some(false),
+            None => return Err(invalid(Column::Direction, direction)),
+        },
+        None => None,
+    };
+
+    let (receiving, amount) = if let Some(signed) = get(Column::SignedAmount) {
+        let value = signed
+            .parse::<i64>()
+            .map_err(|_| invalid(Column::SignedAmount, signed))?;
+        let amount = Csh::try_from(value.unsigned_abs())
+            .map_err(|_| invalid(Column::SignedAmount, signed))?;
+
+        (direction.unwrap_or(!signed.starts_with('-')), amount)
+    } else {
+        let Some(receiving) = direction else {
+            return Err(ExportError::MissingColumn {
+                record,
+                column: Column::Direction.name(),
+            });
+        };
+        let amount = require(Column::Amount)?;
+        let amount = amount
+            .parse::<u32>()
+            .map(Csh::new)
+            .map_err(|_| invalid(Column::Amount, amount))?;
+
+        (receiving, amount)
+    };
+
+    Ok(ImportedLog {
+        owner: get(Column::Owner)
+            .filter(|o| !o.is_empty())
+            .map(str::to_owned),
+        log: TransactionLogV2 {
+            counterparty: require(Column::Counterparty)?.to_owned(),
+            receiving,
+            amount,
+            time,
+        },
+    })
+}
+
+fn direction_name(log: &TransactionLogV2) -> &'static str {
+    if log.receiving {
+        "received"
+    } else {
+        "sent"
+    }
+}
+
+fn parse_direction(direction: &str) -> Option<TransactionDirection> {
+    match direction.to_lowercase().as_str() {
+        "received" => Some(TransactionDirection::Received),
+        "sent" => Some(TransactionDirection::Sent),
+        _ => None,
+    }
+}
+
+fn signed_amount(log: &TransactionLogV2) -> i64 {
+    if log.receiving {
+        log.amount.to_delta()
+    } else {
+        log.amount.to_negative_delta()
+    }
+}
+
+fn to_datetime(time: i64) -> DateTime<Utc> {
+    DateTime::<Utc>::from_utc(
+        NaiveDateTime::from_timestamp_opt(time, 0).unwrap_or_default(),
+        Utc,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(receiving: bool, amount: u32) -> TransactionLogV2 {
+        TransactionLogV2 {
+            counterparty: "bob".into(),
+            receiving,
+            amount: Csh::new(amount),
+            time: 1_672_574_400,
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "csv")]
+    fn keeps_the_direction_of_zero_amounts_in_csv() {
+        let logs = [log(false, 0), log(true, 0), log(false, 25), log(true, 25)];
+        let options = ExportOptions::new().columns(&[
+            Column::Time,
+            Column::Counterparty,
+            Column::SignedAmount,
+        ]);
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &logs, &options).unwrap();
+        let imported = read_csv(csv.as_slice())
+            .unwrap()
+            .into_iter()
+            .map(ImportedLog::into_log)
+            .collect::<Vec<_>>();
+
+        assert_eq!(imported, logs);
+    }
+
+    #[test]
+    fn writes_numeric_amounts_in_ndjson() {
+        let logs = [log(false, 0), log(true, 0), log(false, 25)];
+        let options = ExportOptions::new().columns(&[
+            Column::Time,
+            Column::Counterparty,
+            Column::Direction,
+            Column::SignedAmount,
+        ]);
+
+        let mut ndjson = Vec::new();
+        write_ndjson(&mut ndjson, &logs, &options).unwrap();
+        for line in String::from_utf8(ndjson.clone()).unwrap().lines() {
+            let object: Map<String, Value> = serde_json::from_str(line).unwrap();
+            assert!(object["signed_amount"].is_i64());
+        }
+
+        let imported = read_ndjson(ndjson.as_slice())
+            .unwrap()
+            .into_iter()
+            .map(ImportedLog::into_log)
+            .collect::<Vec<_>>();
+        assert_eq!(imported, logs);
+    }
+
+    #[test]
+    #[cfg(feature = "csv")]
+    fn prefers_the_direction_column() {
+        let csv = "time,counterparty,direction,signed_amount\n0,bob,sent,0\n";
+        let imported = read_csv(csv.as_bytes()).unwrap();
+
+        assert!(!imported[0].get_log().get_if_receiving());
+    }
+}
@@ -0,0 +1,291 @@
+//! This module contains handles that bind a [`CCashUser`] to a
+//! [`CCashSession`], so that the functions in [`methods`] and
+//! [`methods::admin`] can be called as methods without passing the session and
+//! user every time. The handles are implemented on top of these functions, so
+//! both styles can be used interchangeably.
+//!
+//! ```no_run
+//! # async fn run() -> ccash_rs::Result<()> {
+//! use ccash_rs::*;
+//!
+//! let mut session = CCashSession::new("https://bank.example.com");
+//! session.establish_connection().await?;
+//!
+//! let user = CCashUser::new("alice", "password")?;
+//! let account = session.account(&user);
+//! let balance = account.send("bob", 25).await?;
+//! println!("alice now has {balance}");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    history::BalanceHistory,
+    methods,
+    query::LogQuery,
+    watch::{self, BalanceEvent, BalanceWatchOptions, TransactionEvent},
+    CCashSession, CCashUser, Csh, Result, TransactionLogV2, Username,
+};
+use futures::Stream;
+use std::time::Duration;
+
+/// A [`CCashUser`] bound to a [`CCashSession`], created with
+/// [`CCashSession::account`].
+#[derive(Debug, Clone)]
+pub struct AccountHandle<'a> {
+    session: &'a CCashSession,
+    user: CCashUser,
+}
+
+impl<'a> AccountHandle<'a> {
+    /// Constructs a new `AccountHandle` for the `user` on the `session`.
+    #[must_use]
+    pub fn new(session: &'a CCashSession, user: &CCashUser) -> AccountHandle<'a> {
+        Self {
+            session,
+            user: user.clone(),
+        }
+    }
+
+    /// Returns the [`CCashUser`] of this handle, which reflects any password
+    /// changes made through it.
+    #[must_use]
+    pub fn user(&self) -> &CCashUser { &self.user }
+
+    /// Consumes the handle and returns its [`CCashUser`].
+    #[must_use]
+    pub fn into_user(self) -> CCashUser { self.user }
+
+    /// Returns the balance of the user. See [`methods::get_balance`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::get_balance`].
+    pub async fn balance(&self) -> Result<Csh> {
+        methods::get_balance(self.session, &self.user).await
+    }
+
+    /// Returns the transaction logs of the user. See [`methods::get_logs`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::get_logs`].
+    pub async fn logs(&self) -> Result<Vec<TransactionLogV2>> {
+        methods::get_logs(self.session, &self.user).await
+    }
+
+    /// Returns the transaction logs of the user that match the `query`. See
+    /// [`methods::get_log_v2_filtered`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::get_log_v2_filtered`].
+    pub async fn logs_filtered(&self, query: &LogQuery) -> Result<Vec<TransactionLogV2>> {
+        methods::get_log_v2_filtered(self.session, &self.user, query).await
+    }
+
+    /// Returns the balance history of the user. See
+    /// [`methods::get_balance_history`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::get_balance_history`].
+    pub async fn balance_history(&self) -> Result<BalanceHistory> {
+        methods::get_balance_history(self.session, &self.user).await
+    }
+
+    /// Returns a stream of the new transactions of the user, polled every
+    /// `interval`. See [`watch::watch_transactions`].
+    pub fn watch_transactions(
+        &self,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<TransactionEvent>> + 'a {
+        watch::watch_transactions(self.session, &self.user, interval)
+    }
+
+    /// Returns a stream of the balance changes of the user. See
+    /// [`watch::watch_balance`].
+    pub fn watch_balance(
+        &self,
+        options: BalanceWatchOptions,
+    ) -> impl Stream<Item = Result<BalanceEvent>> + 'a {
+        watch::watch_balance(self.session, &self.user, options)
+    }
+
+    /// Returns whether the user exists. See [`methods::contains_user`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::contains_user`].
+    pub async fn exists(&self) -> Result<bool> {
+        methods::contains_user(self.session, &self.user).await
+    }
+
+    /// Returns whether the password of the user is correct. See
+    /// [`methods::verify_password`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::verify_password`].
+    pub async fn verify_password(&self) -> Result<bool> {
+        methods::verify_password(self.session, &self.user).await
+    }
+
+    /// Sends `amount` to the user named `to`, returning the new balance of the
+    /// user. See [`methods::send_funds`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::send_funds`].
+    pub async fn send(&self, to: &str, amount: impl Into<Csh>) -> Result<Csh> {
+        methods::send_funds(self.session, &self.user, to, amount).await
+    }
+
+    /// Changes the password of the user, updating the user of this handle. See
+    /// [`methods::change_password`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::change_password`].
+    pub async fn change_password(&mut self, new_password: &str) -> Result<bool> {
+        methods::change_password(self.session, &mut self.user, new_password).await
+    }
+
+    /// Registers the user with a balance of 0. See [`methods::add_user`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::add_user`].
+    pub async fn register(&self) -> Result<bool> {
+        methods::add_user(self.session, &self.user).await
+    }
+
+    /// Deletes the user. See [`methods::delete_user`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::delete_user`].
+    pub async fn delete(self) -> Result<()> {
+        methods::delete_user(self.session, &self.user).await
+    }
+}
+
+/// The admin [`CCashUser`] bound to a [`CCashSession`], created with
+/// [`CCashSession::admin`].
+///
+/// As [`methods::admin::close`] updates the session, it cannot be called
+/// through this handle.
+#[derive(Debug, Clone)]
+pub struct AdminHandle<'a> {
+    session: &'a CCashSession,
+    admin_user: CCashUser,
+}
+
+impl<'a> AdminHandle<'a> {
+    /// Constructs a new `AdminHandle` for the `admin_user` on the `session`.
+    #[must_use]
+    pub fn new(session: &'a CCashSession, admin_user: &CCashUser) -> AdminHandle<'a> {
+        Self {
+            session,
+            admin_user: admin_user.clone(),
+        }
+    }
+
+    /// Returns the admin [`CCashUser`] of this handle.
+    #[must_use]
+    pub fn admin_user(&self) -> &CCashUser { &self.admin_user }
+
+    /// Returns whether the user of this handle is the admin account. See
+    /// [`methods::admin::verify_account`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::admin::verify_account`].
+    pub async fn verify(&self) -> Result<bool> {
+        methods::admin::verify_account(self.session, &self.admin_user).await
+    }
+
+    /// Changes the password of `user`, updating `user` to use the
+    /// `new_password`. See [`methods::admin::change_password`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::admin::change_password`].
+    pub async fn change_password(
+        &self,
+        user: &mut CCashUser,
+        new_password: &str,
+    ) -> Result<bool> {
+        methods::admin::change_password(
+            self.session,
+            &self.admin_user,
+            user,
+            new_password,
+        )
+        .await
+    }
+
+    /// Sets the balance of the user named `username` to `new_balance`. See
+    /// [`methods::admin::set_balance`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::admin::set_balance`].
+    pub async fn set_balance(
+        &self,
+        username: &Username,
+        new_balance: impl Into<Csh>,
+    ) -> Result<()> {
+        methods::admin::set_balance(self.session, &self.admin_user, username, new_balance)
+            .await
+    }
+
+    /// Impacts the balance of the user named `username` by `amount`. See
+    /// [`methods::admin::impact_balance`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::admin::impact_balance`].
+    pub async fn impact_balance(&self, username: &Username, amount: i64) -> Result<()> {
+        methods::admin::impact_balance(self.session, &self.admin_user, username, amount)
+            .await
+    }
+
+    /// Adds `new_user` with a balance of `amount`. See
+    /// [`methods::admin::add_user`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::admin::add_user`].
+    pub async fn add_user(
+        &self,
+        new_user: &CCashUser,
+        amount: impl Into<Csh>,
+    ) -> Result<bool> {
+        methods::admin::add_user(self.session, &self.admin_user, new_user, amount).await
+    }
+
+    /// Deletes the user named `username`. See
+    /// [`methods::admin::delete_user`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::admin::delete_user`].
+    pub async fn delete_user(&self, username: &Username) -> Result<()> {
+        methods::admin::delete_user(self.session, &self.admin_user, username).await
+    }
+
+    /// Prunes users with less than `amount` in balance, returning the amount of
+    /// users pruned. See [`methods::admin::prune_users`].
+    ///
+    /// # Errors
+    ///
+    /// See [`methods::admin::prune_users`].
+    pub async fn prune_users(
+        &self,
+        amount: impl Into<Csh>,
+        time: Option<i64>,
+    ) -> Result<u64> {
+        methods::admin::prune_users(self.session, &self.admin_user, amount, time).await
+    }
+}
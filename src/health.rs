@@ -0,0 +1,188 @@
+//! This module contains the [`HealthMonitor`], which checks that the `CCash`
+//! instance of a [`CCashSession`] is available in the background, and
+//! re-establishes the connection when the instance comes back.
+//!
+//! When the instance cannot be reached, the session is marked as not
+//! connected, so requests first try to re-establish the connection (see
+//! [`RetryPolicy::with_reconnect`](crate::RetryPolicy::with_reconnect)), or
+//! fail fast with [`CCashError::ConnectionNotAvailable`] if reconnecting is
+//! turned off. Once it responds again, the connection is re-established and
+//! the properties of the session are refreshed. Errors from requests can be
+//! [`report`](HealthMonitor::report)ed to check the instance straight away
+//! rather than at the next interval.
+//!
+//! ```no_run
+//! # async fn run() -> ccash_rs::Result<()> {
+//! use ccash_rs::{health::*, *};
+//! use std::{sync::Arc, time::Duration};
+//! use tokio::sync::RwLock;
+//!
+//! let mut session = CCashSession::new("https://bank.example.com");
+//! session.establish_connection().await?;
+//! let session = Arc::new(RwLock::new(session));
+//! let monitor = HealthMonitor::spawn(Arc::clone(&session), Duration::from_secs(10));
+//!
+//! let shop = CCashUser::new("shop", "password")?;
+//! match methods::get_balance(&*session.read().await, &shop).await {
+//!     Ok(balance) => println!("{balance}"),
+//!     Err(e) => monitor.report(&e),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{CCashError, CCashSession};
+use chrono::{DateTime, Utc};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{watch, Notify, RwLock},
+    task::JoinHandle,
+};
+
+/// Enum for the health of a `CCash` instance, as of the last check made by a
+/// [`HealthMonitor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// The instance has not been checked yet.
+    Unknown,
+    /// The instance responded to the last check.
+    Up {
+        /// The time it took for the instance to respond.
+        latency: Duration,
+        /// The time the instance was checked.
+        checked_at: DateTime<Utc>,
+    },
+    /// The instance could not be reached at the last check.
+    Down {
+        /// The reason the instance could not be reached.
+        reason: String,
+        /// The time the instance was first found to be down.
+        since: DateTime<Utc>,
+    },
+}
+
+impl HealthStatus {
+    /// Returns whether the instance responded to the last check.
+    #[must_use]
+    pub fn is_up(&self) -> bool { matches!(self, HealthStatus::Up { .. }) }
+}
+
+/// A background task that checks the `CCash` instance of a [`CCashSession`]
+/// every interval, and re-establishes the connection when the instance comes
+/// back. The task is stopped when the `HealthMonitor` is dropped.
+#[derive(Debug)]
+pub struct HealthMonitor {
+    status: watch::Receiver<HealthStatus>,
+    wake: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl HealthMonitor {
+    /// Spawns a `HealthMonitor` for the `session` onto the current Tokio
+    /// runtime, checking the `CCash` instance every `interval`. A check that
+    /// takes longer than `interval` counts as the instance being down.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if called outside of a Tokio runtime.
+    #[must_use]
+    pub fn spawn(
+        session: Arc<RwLock<CCashSession>>,
+        interval: Duration,
+    ) -> HealthMonitor {
+        let (sender, status) = watch::channel(HealthStatus::Unknown);
+        let wake = Arc::new(Notify::new());
+        let task = tokio::spawn(run(session, interval, sender, Arc::clone(&wake)));
+
+        HealthMonitor { status, wake, task }
+    }
+
+    /// Returns the health of the `CCash` instance as of the last check.
+    #[must_use]
+    pub fn status(&self) -> HealthStatus { self.status.borrow().clone() }
+
+    /// Returns a receiver that is notified whenever the health of the `CCash`
+    /// instance is checked.
+    #[must_use]
+    pub fn subscribe(&self) -> watch::Receiver<HealthStatus> { self.status.clone() }
+
+    /// Checks the `CCash` instance straight away rather than at the next
+    /// interval.
+    pub fn check_now(&self) { self.wake.notify_one(); }
+
+    /// Reports an `error` returned by a request, checking the `CCash` instance
+    /// straight away if the error was caused by it being unreachable.
+    pub fn report(&self, error: &CCashError) {
+        if error.is_connection_error() {
+            self.check_now();
+        }
+    }
+}
+
+impl Drop for HealthMonitor {
+    fn drop(&mut self) { self.task.abort(); }
+}
+
+async fn run(
+    session: Arc<RwLock<CCashSession>>,
+    interval: Duration,
+    sender: watch::Sender<HealthStatus>,
+    wake: Arc<Notify>,
+) {
+    loop {
+        let was_up = sender.borrow().is_up();
+        let start = Instant::now();
+
+        // The check is made on a copy of the session, so that requests using
+        // it aren't blocked while the instance is slow to respond.
+        let mut checked = session.read().await.clone();
+        let result = tokio::time::timeout(interval, check(&mut checked, was_up))
+            .await
+            .unwrap_or(Err(CCashError::ConnectionNotAvailable))
+            .map(|()| start.elapsed());
+
+        // Only the connection is swapped in, so that other changes made to the
+        // session during the check are kept.
+        {
+            let checked = checked.connection.read().clone();
+            let session = session.read().await;
+            let mut state = session.connection.write();
+            match (&result, checked.client, checked.properties) {
+                (Ok(_), Some(client), Some(properties)) =>
+                    state.connect(client, properties),
+                _ => state.is_connected = false,
+            }
+        }
+
+        let status = match result {
+            Ok(latency) => HealthStatus::Up {
+                latency,
+                checked_at: Utc::now(),
+            },
+            Err(e) => HealthStatus::Down {
+                reason: e.to_string(),
+                since: match &*sender.borrow() {
+                    HealthStatus::Down { since, .. } => *since,
+                    _ => Utc::now(),
+                },
+            },
+        };
+        sender.send_replace(status);
+
+        let _ = tokio::time::timeout(interval, wake.notified()).await;
+    }
+}
+
+/// Checks the `CCash` instance of the `session`. While the instance is up and
+/// connected a ping is enough, otherwise the connection is re-established,
+/// which also refreshes the properties of the `session`.
+async fn check(session: &mut CCashSession, was_up: bool) -> Result<(), CCashError> {
+    if was_up && session.is_connected() && session.ping().await.is_ok() {
+        return Ok(());
+    }
+
+    session.reconnect().await
+}
@@ -0,0 +1,174 @@
+//! This module contains [`BalanceHistory`], which reconstructs the balance of a
+//! user over time from their current balance and their [`TransactionLogV2`]
+//! entries.
+//!
+//! Starting from the current balance, every logged transaction is undone in
+//! order from newest to oldest, giving the balance after each transaction. This
+//! has two limits, which `BalanceHistory` flags:
+//! - `CCash` only keeps the newest `max_log` entries per user, so the history
+//!   may not reach back to when the account was created
+//!   ([`is_truncated`](BalanceHistory::is_truncated)).
+//! - Admin changes made with
+//!   [`set_balance`](crate::methods::admin::set_balance) and
+//!   [`impact_balance`](crate::methods::admin::impact_balance) are not logged,
+//!   so balances before such a change are off by its amount. This can only be
+//!   detected when it leads to an impossible balance
+//!   ([`has_unlogged_adjustments`](BalanceHistory::has_unlogged_adjustments)).
+
+use crate::{Csh, TransactionLogV2};
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// The balance of a user directly after a logged transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalancePoint {
+    pub(crate) time: DateTime<Utc>,
+    pub(crate) balance_after: i64,
+    pub(crate) entry: TransactionLogV2,
+}
+
+impl BalancePoint {
+    /// Returns the time of the transaction.
+    #[must_use]
+    pub fn get_time(&self) -> DateTime<Utc> { self.time }
+
+    /// Returns the balance of the user directly after the transaction. This is
+    /// signed, as unlogged admin changes can lead to a negative reconstructed
+    /// balance.
+    #[must_use]
+    pub fn get_balance_after(&self) -> i64 { self.balance_after }
+
+    /// Returns the log entry of the transaction.
+    #[must_use]
+    pub fn get_entry(&self) -> &TransactionLogV2 { &self.entry }
+}
+
+/// The balance of a user over time, reconstructed from their transaction logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceHistory {
+    current_balance: Csh,
+    opening_balance: i64,
+    points: Vec<BalancePoint>,
+    is_truncated: bool,
+    has_unlogged_adjustments: bool,
+    pub(crate) is_consistent: bool,
+}
+
+impl BalanceHistory {
+    /// Reconstructs the `BalanceHistory` of a user from their
+    /// `current_balance` and their `logs`, in the order returned by
+    /// [`get_log_v2`](crate::methods::get_log_v2). `max_log` is the max amount
+    /// of logs kept by the `CCash` instance (see
+    /// [`CCashSessionProperties::get_max_log`](crate::CCashSessionProperties::get_max_log)),
+    /// and if it is `None` the history is assumed to be truncated.
+    #[must_use]
+    pub fn from_logs(
+        current_balance: Csh,
+        mut logs: Vec<TransactionLogV2>,
+        max_log: Option<u32>,
+    ) -> BalanceHistory {
+        let is_truncated = max_log.is_none_or(|max_log| logs.len() >= max_log as usize);
+
+        // `CCash` returns the newest transactions first, so reversing keeps
+        // transactions made in the same second in order.
+        logs.reverse();
+        logs.sort_by_key(|log| log.time);
+
+        let mut balance = current_balance.to_delta();
+        let mut has_unlogged_adjustments = false;
+        let mut points = Vec::with_capacity(logs.len());
+
+        for entry in logs.into_iter().rev() {
+            if balance < 0 || balance > Csh::MAX.to_delta() {
+                has_unlogged_adjustments = true;
+            }
+
+            let balance_after = balance;
+            if entry.receiving {
+                balance -= entry.amount.to_delta();
+            } else {
+                balance += entry.amount.to_delta();
+            }
+
+            points.push(BalancePoint {
+                time: to_datetime(entry.time),
+                balance_after,
+                entry,
+            });
+        }
+
+        if balance < 0 || balance > Csh::MAX.to_delta() {
+            has_unlogged_adjustments = true;
+        }
+        points.reverse();
+
+        BalanceHistory {
+            current_balance,
+            opening_balance: balance,
+            points,
+            is_truncated,
+            has_unlogged_adjustments,
+            is_consistent: true,
+        }
+    }
+
+    /// Returns the balance of the user after each logged transaction, oldest
+    /// first.
+    #[must_use]
+    pub fn get_points(&self) -> &[BalancePoint] { &self.points }
+
+    /// Returns the current balance of the user.
+    #[must_use]
+    pub fn get_current_balance(&self) -> Csh { self.current_balance }
+
+    /// Returns the balance of the user before the oldest logged transaction.
+    /// If the history [`is_truncated`](BalanceHistory::is_truncated), this is
+    /// not the balance the account was created with.
+    #[must_use]
+    pub fn get_opening_balance(&self) -> i64 { self.opening_balance }
+
+    /// Returns the time of the oldest logged transaction, if any.
+    #[must_use]
+    pub fn get_earliest_time(&self) -> Option<DateTime<Utc>> {
+        self.points.first().map(|p| p.time)
+    }
+
+    /// Returns whether the logs may have been cut off by the `max_log` limit of
+    /// the `CCash` instance, meaning that older transactions are missing.
+    #[must_use]
+    pub fn is_truncated(&self) -> bool { self.is_truncated }
+
+    /// Returns whether the reconstructed balance was negative or too large at
+    /// some point, which means an admin changed the balance without it being
+    /// logged. Balances before that change are then unreliable.
+    #[must_use]
+    pub fn has_unlogged_adjustments(&self) -> bool { self.has_unlogged_adjustments }
+
+    /// Returns whether the current balance is known to match the logs. This is
+    /// `false` if the balance kept changing while the logs were fetched by
+    /// [`get_balance_history`](crate::methods::get_balance_history), in which
+    /// case the newest transactions may be missing or the balances after each
+    /// transaction may be off.
+    #[must_use]
+    pub fn is_consistent(&self) -> bool { self.is_consistent }
+
+    /// Returns the balance of the user at `time`, assuming no unlogged admin
+    /// changes were made since. Returns `None` if `time` is before the oldest
+    /// logged transaction and the history
+    /// [`is_truncated`](BalanceHistory::is_truncated), as the balance at that
+    /// time is unknown.
+    #[must_use]
+    pub fn balance_at(&self, time: DateTime<Utc>) -> Option<i64> {
+        match self.points.iter().rev().find(|p| p.time <= time) {
+            Some(point) => Some(point.balance_after),
+            None if self.is_truncated => None,
+            None => Some(self.opening_balance),
+        }
+    }
+}
+
+fn to_datetime(time: i64) -> DateTime<Utc> {
+    DateTime::<Utc>::from_utc(
+        NaiveDateTime::from_timestamp_opt(time, 0).unwrap_or_default(),
+        Utc,
+    )
+}
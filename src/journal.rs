@@ -0,0 +1,441 @@
+//! This module contains the [`TransferJournal`], which makes
+//! [`send_funds`](methods::send_funds) idempotent with a client-supplied key.
+//!
+//! If a transfer fails in a way where it is unclear whether the `CCash`
+//! instance applied it, such as a timeout, retrying risks paying twice. Before
+//! sending, [`send_funds_idempotent`] records the transfer and a snapshot of
+//! the balance and logs of the sender in the journal file. If the outcome is
+//! unclear, these are fetched again and compared with the snapshot to decide
+//! whether the transfer landed, and it is only sent again if they prove that it
+//! did not. A transfer that completed is never sent again for the same key,
+//! even across restarts.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use ccash_rs::{journal::*, *};
+//!
+//! let mut session = CCashSession::new("https://bank.example.com");
+//! session.establish_connection().await?;
+//! let shop = CCashUser::new("shop", "password")?;
+//! let mut journal = TransferJournal::open("transfers.ndjson")?;
+//!
+//! // Safe to call again with the same key if the first call failed.
+//! send_funds_idempotent(&session, &mut journal, &shop, "refund-1042", "alice", 25)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    methods, watch::count_new_logs, CCashApiError, CCashError, CCashSession,
+    CCashSessionProperties, CCashUser, Csh, TransactionLogV2,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The max amount of times a transfer is sent by a single call to
+/// [`send_funds_idempotent`].
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Enum for the errors that could occur when making an idempotent transfer.
+#[derive(Error, Debug)]
+pub enum JournalError {
+    /// An error when reading or writing the journal file.
+    #[error("Could not access the transfer journal: {0}")]
+    Io(#[from] std::io::Error),
+    /// An error when the journal file contains an invalid entry.
+    #[error("The transfer journal contains an invalid entry: {0}")]
+    Json(#[from] serde_json::Error),
+    /// An error when a key is reused for a different transfer.
+    #[error("The key \"{0}\" was already used for a different transfer")]
+    KeyReused(String),
+    /// An error when it could not be decided whether the transfer landed, as
+    /// the logs of the sender cannot prove that it did not, or its balance
+    /// dropped by more than the logs account for. The transfer is left pending,
+    /// and is not sent again until it is resolved by hand.
+    #[error("Could not decide whether the transfer with key \"{0}\" landed")]
+    Unresolved(String),
+    /// An error returned by the `CCash` instance, or when it could not be
+    /// reached. If the transfer is pending, calling
+    /// [`send_funds_idempotent`] again with the same key reconciles it.
+    #[error("{0}")]
+    CCash(#[from] CCashError),
+}
+
+/// Enum for the state of a transfer in a [`TransferJournal`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TransferState {
+    /// The transfer is about to be sent, or it is unclear whether it landed.
+    Pending,
+    /// The transfer landed.
+    Completed {
+        /// The balance of the sender when the transfer was confirmed.
+        balance: Csh,
+    },
+    /// The `CCash` instance rejected the transfer, so it did not land. The
+    /// transfer is sent again if the same key is used.
+    Failed {
+        /// The reason the transfer was rejected.
+        reason: String,
+    },
+}
+
+/// A transfer recorded in a [`TransferJournal`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    key: String,
+    sender: String,
+    recipient: String,
+    amount: Csh,
+    time: i64,
+    #[serde(flatten)]
+    state: TransferState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snapshot_balance: Option<Csh>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    snapshot: Vec<TransactionLogV2>,
+}
+
+impl JournalEntry {
+    /// Returns the idempotency key of the transfer.
+    #[must_use]
+    pub fn get_key(&self) -> &str { &self.key }
+
+    /// Returns the name of the sender.
+    #[must_use]
+    pub fn get_sender(&self) -> &str { &self.sender }
+
+    /// Returns the name of the recipient.
+    #[must_use]
+    pub fn get_recipient(&self) -> &str { &self.recipient }
+
+    /// Returns the amount of the transfer.
+    #[must_use]
+    pub fn get_amount(&self) -> Csh { self.amount }
+
+    /// Returns the time the state of the transfer last changed, in Unix epoch
+    /// time.
+    #[must_use]
+    pub fn get_time(&self) -> i64 { self.time }
+
+    /// Returns the state of the transfer.
+    #[must_use]
+    pub fn get_state(&self) -> &TransferState { &self.state }
+
+    fn clear_snapshot(&mut self) {
+        self.snapshot_balance = None;
+        self.snapshot.clear();
+    }
+
+    fn is_same_transfer(&self, sender: &str, recipient: &str, amount: Csh) -> bool {
+        self.sender == sender && self.recipient == recipient && self.amount == amount
+    }
+}
+
+/// Enum for the outcome of [`send_funds_idempotent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferOutcome {
+    /// The transfer was sent and the `CCash` instance confirmed it.
+    Sent {
+        /// The balance of the sender after the transfer.
+        balance: Csh,
+    },
+    /// The outcome of sending the transfer was unclear, and the logs of the
+    /// sender showed that it landed.
+    Reconciled {
+        /// The balance of the sender when the transfer was confirmed.
+        balance: Csh,
+    },
+    /// The transfer had already completed with the same key, so it was not
+    /// sent again.
+    AlreadyCompleted {
+        /// The balance of the sender when the transfer was confirmed.
+        balance: Csh,
+    },
+}
+
+/// An append-only journal of transfers, stored as JSON Lines in a local file.
+/// Every change of state is appended and synced to disk before the next
+/// request is made, so the journal survives crashes.
+#[derive(Debug)]
+pub struct TransferJournal {
+    path: PathBuf,
+    entries: HashMap<String, JournalEntry>,
+}
+
+impl TransferJournal {
+    /// Opens the journal at `path`, creating it if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`JournalError`] if the file cannot be read, or contains
+    /// an invalid entry.
+    pub fn open(path: impl AsRef<Path>) -> Result<TransferJournal, JournalError> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = HashMap::new();
+
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let entry: JournalEntry = serde_json::from_str(&line)?;
+                entries.insert(entry.key.clone(), entry);
+            }
+        }
+
+        Ok(TransferJournal { path, entries })
+    }
+
+    /// Returns the latest state of the transfer with the given `key`.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&JournalEntry> { self.entries.get(key) }
+
+    /// Returns the latest state of every transfer in the journal.
+    pub fn entries(&self) -> impl Iterator<Item = &JournalEntry> { self.entries.values() }
+
+    /// Returns the transfers whose outcome is still unclear.
+    pub fn pending(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.entries
+            .values()
+            .filter(|e| e.state == TransferState::Pending)
+    }
+
+    fn record(&mut self, mut entry: JournalEntry) -> Result<(), JournalError> {
+        entry.time = Utc::now().timestamp();
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        file.sync_data()?;
+
+        self.entries.insert(entry.key.clone(), entry);
+        Ok(())
+    }
+}
+
+/// Sends `amount` from the `user` to the user named `recipient_name` at most
+/// once for the given `key`, recording the transfer in the `journal`.
+///
+/// If the transfer already completed with this `key`, it is not sent again. If
+/// it is pending from an earlier call whose outcome was unclear, the balance
+/// and logs of the `user` are compared with the snapshot taken before sending,
+/// and the transfer is only sent again if they prove that it did not land. This
+/// is not possible if the `CCash` instance keeps no logs, or if more
+/// transactions were made since the snapshot than its `max_log` window can
+/// tell apart, in which case [`JournalError::Unresolved`] is returned.
+/// Transfers are matched by recipient and amount, so another client making the
+/// same transfer from the same account at the same time could be mistaken for
+/// this one.
+///
+/// # Errors
+///
+/// Will return a [`JournalError`] if the journal cannot be written, if the
+/// `key` was used for a different transfer, if the `CCash` instance rejects
+/// the transfer or cannot be reached, or if it could not be decided whether
+/// the transfer landed.
+pub async fn send_funds_idempotent(
+    session: &CCashSession,
+    journal: &mut TransferJournal,
+    user: &CCashUser,
+    key: &str,
+    recipient_name: &str,
+    amount: impl Into<Csh>,
+) -> Result<TransferOutcome, JournalError> {
+    let recipient_name = recipient_name.to_lowercase();
+    let amount = amount.into();
+
+    let mut entry = match journal.get(key) {
+        Some(entry)
+            if !entry.is_same_transfer(&user.username, &recipient_name, amount) =>
+            return Err(JournalError::KeyReused(key.into())),
+        Some(JournalEntry {
+            state: TransferState::Completed { balance },
+            ..
+        }) => return Ok(TransferOutcome::AlreadyCompleted { balance: *balance }),
+        Some(
+            entry @ JournalEntry {
+                state: TransferState::Pending,
+                ..
+            },
+        ) => {
+            let mut entry = entry.clone();
+            if let Some(outcome) = reconcile(session, journal, user, &mut entry).await? {
+                return Ok(outcome);
+            }
+            entry
+        },
+        _ => {
+            let entry = JournalEntry {
+                key: key.into(),
+                sender: user.username.clone(),
+                recipient: recipient_name.clone(),
+                amount,
+                time: 0,
+                state: TransferState::Pending,
+                snapshot_balance: Some(methods::get_balance(session, user).await?),
+                snapshot: methods::get_logs(session, user).await?,
+            };
+            journal.record(entry.clone())?;
+            entry
+        },
+    };
+
+    let mut attempt = 1;
+    loop {
+        match methods::send_funds(session, user, &recipient_name, amount).await {
+            Ok(balance) => {
+                entry.state = TransferState::Completed { balance };
+                entry.clear_snapshot();
+                journal.record(entry)?;
+                return Ok(TransferOutcome::Sent { balance });
+            },
+            Err(e) if !is_uncertain(&e) => {
+                entry.state = TransferState::Failed {
+                    reason: e.to_string(),
+                };
+                entry.clear_snapshot();
+                journal.record(entry)?;
+                return Err(e.into());
+            },
+            Err(e) if attempt >= MAX_ATTEMPTS => return Err(e.into()),
+            Err(_) => {
+                if let Some(outcome) =
+                    reconcile(session, journal, user, &mut entry).await?
+                {
+                    return Ok(outcome);
+                }
+                attempt += 1;
+            },
+        }
+    }
+}
+
+/// Decides whether the pending transfer in `entry` landed, by comparing the
+/// current balance and logs of the `user` with the snapshot taken before it was
+/// sent. Returns the outcome if it landed, or `None` if it provably did not, in
+/// which case the snapshot of the `entry` is replaced so that it can be sent
+/// again.
+async fn reconcile(
+    session: &CCashSession,
+    journal: &mut TransferJournal,
+    user: &CCashUser,
+    entry: &mut JournalEntry,
+) -> Result<Option<TransferOutcome>, JournalError> {
+    let balance = methods::get_balance(session, user).await?;
+    let logs = methods::get_logs(session, user).await?;
+    let new = count_new_logs(&entry.snapshot, &logs);
+
+    let landed = logs[..new].iter().any(|log| {
+        !log.receiving
+            && log.counterparty == entry.recipient
+            && log.amount == entry.amount
+    });
+
+    if landed {
+        entry.state = TransferState::Completed { balance };
+        entry.clear_snapshot();
+        journal.record(entry.clone())?;
+        return Ok(Some(TransferOutcome::Reconciled { balance }));
+    }
+
+    let max_log = session
+        .get_properties()
+        .as_ref()
+        .map_or(0, CCashSessionProperties::get_max_log);
+    if !is_conclusive(&entry.snapshot, &logs, max_log)
+        || has_unlogged_debit(entry, balance, &logs[..new])
+    {
+        return Err(JournalError::Unresolved(entry.key.clone()));
+    }
+
+    entry.snapshot_balance = Some(balance);
+    entry.snapshot = logs;
+    journal.record(entry.clone())?;
+    Ok(None)
+}
+
+/// Returns whether the `logs` show every transaction made since the
+/// `snapshot`, so that a transfer missing from them did not land.
+///
+/// If the logs are not full, no entry was pushed out of the `max_log` window.
+/// Otherwise, the new entries are only known if the logs line up with the
+/// snapshot in exactly one way, as identical transactions made in the same
+/// second would line up in several, and logs that do not line up at all may
+/// have pushed every entry of the snapshot out of the window.
+fn is_conclusive(
+    snapshot: &[TransactionLogV2],
+    logs: &[TransactionLogV2],
+    max_log: u32,
+) -> bool {
+    if max_log == 0 {
+        return false;
+    }
+    if logs.len() < max_log as usize {
+        return true;
+    }
+
+    let mut alignments = (0..logs.len()).filter(|&new| {
+        let overlap = logs.len() - new;
+        overlap <= snapshot.len() && logs[new..] == snapshot[..overlap]
+    });
+
+    alignments.next().is_some() && alignments.next().is_none()
+}
+
+/// Returns whether the `balance` of the sender dropped by at least the amount
+/// of the `entry` more than the `new_logs` since the snapshot account for, in
+/// which case the transfer may have landed without being logged.
+fn has_unlogged_debit(
+    entry: &JournalEntry,
+    balance: Csh,
+    new_logs: &[TransactionLogV2],
+) -> bool {
+    let Some(snapshot_balance) = entry.snapshot_balance else {
+        return false;
+    };
+
+    let logged = new_logs
+        .iter()
+        .map(|log| {
+            if log.receiving {
+                log.amount.to_delta()
+            } else {
+                log.amount.to_negative_delta()
+            }
+        })
+        .sum::<i64>();
+    let unlogged = balance.to_delta() - snapshot_balance.to_delta() - logged;
+
+    !entry.amount.is_zero() && unlogged <= entry.amount.to_negative_delta()
+}
+
+/// Returns whether it is unclear if the `CCash` instance applied a request
+/// that failed with the `error`.
+fn is_uncertain(error: &CCashError) -> bool {
+    match error {
+        CCashError::UsernameError(_)
+        | CCashError::ConnectionNotAvailable
+        | CCashError::UnsupportedByServer(_) => false,
+        CCashError::ReqwestError(e) => !e.is_connect(),
+        CCashError::ApiError(e) => matches!(
+            e,
+            CCashApiError::ServerError { .. } | CCashApiError::Unexpected { .. }
+        ),
+        _ => true,
+    }
+}
@@ -2,22 +2,61 @@
 #![allow(clippy::module_name_repetitions, deprecated)]
 #![doc = include_str!("../README.md")]
 
+mod connection;
 #[macro_use]
 mod request;
+pub mod amount;
+pub mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod builder;
+#[cfg(feature = "csv")]
+pub mod bulk;
+pub mod export;
+pub mod handle;
+#[cfg(feature = "health")]
+pub mod health;
+pub mod history;
+pub mod journal;
 pub mod methods;
+#[cfg(feature = "test-util")]
+pub mod mock;
+pub mod payout;
+pub mod pool;
+#[cfg(feature = "profiles")]
+pub mod profile;
+pub mod query;
 pub mod responses;
+pub mod retry;
 pub mod user;
+pub mod watch;
 
-pub use crate::{responses::*, user::*};
+pub use crate::{
+    amount::{Csh, CshError, CshFormat},
+    api::CCashApi,
+    builder::CCashSessionBuilder,
+    handle::{AccountHandle, AdminHandle},
+    responses::*,
+    retry::RetryPolicy,
+    user::*,
+};
+use builder::ClientConfig;
 use chrono::prelude::*;
+use connection::Connection;
 use reqwest::Client;
-use serde::Deserialize;
-use std::fmt;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// The max delay between attempts of [`CCashSession::wait_until_available`].
+pub(crate) const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
 
 /// Struct that decribes the properties of the `CCash` instance that are
 /// returned from the `properties` endpoint. Helps define the behaviour of this
 /// API.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CCashSessionProperties {
     pub(crate) version: Option<u32>,
     pub(crate) max_log: u32,
@@ -53,16 +92,47 @@ impl CCashSessionProperties {
     /// `CCash` on all instances.
     #[must_use]
     pub fn get_return_on_delete_account(&self) -> &Option<String> { &self.return_on_del }
+
+    /// Returns whether the `CCash` instance supports the `capability`. An
+    /// instance that does not report its version is assumed to support every
+    /// version, so that requests to it are still attempted.
+    #[must_use]
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::LogV2 => !matches!(self.version, Some(version) if version < 2),
+            Capability::OpenRegistration => self.get_add_user_is_open(),
+        }
+    }
+}
+
+/// Enum for the features of the `CCash` API that are not available on every
+/// `CCash` instance, negotiated from the properties of the instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// The v2 transaction logs endpoint, available from version 2 of `CCash`.
+    LogV2,
+    /// Registration of new users without the admin account, which can be
+    /// closed by the host of the `CCash` instance.
+    OpenRegistration,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capability::LogV2 => write!(f, "v2 transaction logs"),
+            Capability::OpenRegistration => write!(f, "open user registration"),
+        }
+    }
 }
 
 /// Struct that describes the format of the logs returned by
 /// [`get_log`](`methods::get_log`).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[deprecated(since = "2.0.0", note = "Prefer the usage of `TransactionLogV2`")]
 pub struct TransactionLog {
     pub(crate) to: String,
     pub(crate) from: String,
-    pub(crate) amount: u32,
+    pub(crate) amount: Csh,
     pub(crate) time: i64,
 }
 
@@ -77,11 +147,25 @@ impl TransactionLog {
 
     /// Returns the amount of CSH that was sent.
     #[must_use]
-    pub fn get_amount(&self) -> u32 { self.amount }
+    pub fn get_amount(&self) -> Csh { self.amount }
 
     /// Returns the time that the funds were sent in Unix epoch time.
     #[must_use]
     pub fn get_time(&self) -> i64 { self.time }
+
+    /// Converts this log into a [`TransactionLogV2`] from the point of view of
+    /// the account named `owner`.
+    #[must_use]
+    pub fn to_v2(&self, owner: &str) -> TransactionLogV2 {
+        let receiving = self.to.eq_ignore_ascii_case(owner);
+
+        TransactionLogV2 {
+            counterparty: if receiving { &self.from } else { &self.to }.clone(),
+            receiving,
+            amount: self.amount,
+            time: self.time,
+        }
+    }
 }
 
 impl fmt::Display for TransactionLog {
@@ -92,7 +176,7 @@ impl fmt::Display for TransactionLog {
         );
         write!(
             f,
-            "{}: {} ({} CSH) -> {}",
+            "{}: {} ({}) -> {}",
             time, &self.from, self.amount, &self.to,
         )
     }
@@ -100,11 +184,11 @@ impl fmt::Display for TransactionLog {
 
 /// Struct that describes the format of the logs returned by
 /// [`get_log_v2`](`methods::get_log_v2`).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TransactionLogV2 {
     pub(crate) counterparty: String,
     pub(crate) receiving: bool,
-    pub(crate) amount: u32,
+    pub(crate) amount: Csh,
     pub(crate) time: i64,
 }
 
@@ -121,7 +205,7 @@ impl TransactionLogV2 {
 
     /// Returns the amount of funds in CSH.
     #[must_use]
-    pub fn get_amount(&self) -> u32 { self.amount }
+    pub fn get_amount(&self) -> Csh { self.amount }
 
     /// Returns the time of the transaction in Unix epoch time.
     #[must_use]
@@ -139,7 +223,7 @@ impl fmt::Display for TransactionLogV2 {
 
         write!(
             f,
-            "{time}: {action} {} CSH {tofrom} {}",
+            "{time}: {action} {} {tofrom} {}",
             self.amount, self.counterparty
         )
     }
@@ -156,7 +240,7 @@ impl fmt::Display for TransactionLogV2 {
 ///
 /// An example usage is as follows
 /// (available [here](https://github.com/STBoyden/ccash-rs/src/branch/master/examples/get_balance.rs)):
-/// ```
+/// ```no_run
 #[doc = include_str!("../examples/get_balance.rs")]
 /// ```
 /// 
@@ -168,27 +252,55 @@ impl fmt::Display for TransactionLogV2 {
 #[derive(Debug, Clone)]
 pub struct CCashSession {
     session_url: String,
-    is_connected: bool,
-    client: Option<Client>,
-    properties: Option<CCashSessionProperties>,
+    connection: Connection<Client>,
+    client_config: ClientConfig,
+    retry_policy: RetryPolicy,
 }
 
 impl CCashSession {
     /// Constructs a new `CCashSession` from a `base_url`
     #[must_use]
     pub fn new(base_url: &str) -> CCashSession {
-        let base_url = if base_url.ends_with('/') {
-            base_url.trim_end_matches('/')
-        } else {
-            base_url
-        };
+        CCashSessionBuilder::new(base_url).build()
+    }
 
-        Self {
-            session_url: format!("{base_url}/api"),
-            is_connected: false,
-            client: None,
-            properties: None,
-        }
+    /// Returns a [`CCashSessionBuilder`] for the `CCash` instance at
+    /// `base_url`, to configure timeouts, proxies, certificates, the user
+    /// agent, or the client used by the `CCashSession`.
+    #[must_use]
+    pub fn builder(base_url: &str) -> CCashSessionBuilder {
+        CCashSessionBuilder::new(base_url)
+    }
+
+    /// Sets the [`RetryPolicy`] used for requests made with this
+    /// `CCashSession`. By default, requests are never retried, but
+    /// the connection is re-established when they cannot reach the instance.
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> CCashSession {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] used for requests made with this
+    /// `CCashSession`.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Returns an [`AccountHandle`] that binds the `user` to this
+    /// `CCashSession`, so that the functions in [`methods`] can be called as
+    /// methods.
+    #[must_use]
+    pub fn account(&self, user: &CCashUser) -> AccountHandle<'_> {
+        AccountHandle::new(self, user)
+    }
+
+    /// Returns an [`AdminHandle`] that binds the `admin_user` to this
+    /// `CCashSession`, so that the functions in [`methods::admin`] can be
+    /// called as methods.
+    #[must_use]
+    pub fn admin(&self, admin_user: &CCashUser) -> AdminHandle<'_> {
+        AdminHandle::new(self, admin_user)
     }
 
     /// Establishes a connection to the `CCash` instance using the
@@ -198,13 +310,120 @@ impl CCashSession {
     ///
     /// Will return [`CCashError::CouldNotParsePropertiesResponse`] if the
     /// properties returned by `CCash` could not be parsed correctly.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "info",
+            skip_all,
+            fields(url = %self.session_url),
+            err(Display)
+        )
+    )]
     pub async fn establish_connection(&mut self) -> Result<()> {
-        if self.is_connected {
+        if self.is_connected() {
             return Ok(());
         }
 
-        let client = Client::builder().build()?;
+        let client = self.client_config.build()?;
+        let properties = self.fetch_properties(&client).await?;
 
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            version = properties.version,
+            max_log = properties.max_log,
+            "connected to CCash instance"
+        );
+        self.connection.write().connect(client, properties);
+        Ok(())
+    }
+
+    /// Checks that the `CCash` instance is available by requesting its
+    /// properties, returning the time it took to respond. This does not change
+    /// the state of this `CCashSession`, and can be used before the connection
+    /// has been established.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`CCashError`] if the `CCash` instance cannot be reached, or
+    /// [`CCashError::CouldNotParsePropertiesResponse`] if the properties it
+    /// returned could not be parsed correctly.
+    pub async fn ping(&self) -> Result<Duration> {
+        let client = self.client_or_build()?;
+        let start = Instant::now();
+        self.fetch_properties(&client).await?;
+        Ok(start.elapsed())
+    }
+
+    /// Re-establishes the connection to the `CCash` instance, refreshing its
+    /// properties even if this `CCashSession` is already connected. If the
+    /// instance cannot be reached, this `CCashSession` is marked as not
+    /// connected until the connection is established again.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`CCashError`] for the same reasons as [`ping`](Self::ping).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "info",
+            skip_all,
+            fields(url = %self.session_url),
+            err(Display, level = "warn")
+        )
+    )]
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let client = self.client_or_build()?;
+
+        match self.fetch_properties(&client).await {
+            Ok(properties) => {
+                self.connection.write().connect(client, properties);
+                Ok(())
+            },
+            Err(e) => {
+                self.connection.write().is_connected = false;
+                Err(e)
+            },
+        }
+    }
+
+    /// Waits until the `CCash` instance is available and re-establishes the
+    /// connection to it, trying again with an increasing delay for up to
+    /// `timeout`. This is useful after the instance has been restarted, for
+    /// example after [`admin::close`](methods::admin::close).
+    ///
+    /// # Errors
+    ///
+    /// Will return the last [`CCashError`] returned by
+    /// [`reconnect`](Self::reconnect) if the `CCash` instance did not become
+    /// available within `timeout`, or [`CCashError::ConnectionNotAvailable`] if
+    /// the last attempt did not finish in time.
+    pub async fn wait_until_available(&mut self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut delay = Duration::from_millis(250);
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let error = match tokio::time::timeout(remaining, self.reconnect()).await {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(e)) => e,
+                Err(_) => return Err(CCashError::ConnectionNotAvailable),
+            };
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(error);
+            }
+
+            tokio::time::sleep(delay.min(remaining)).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Fetches the properties of the `CCash` instance with the `client`.
+    pub(crate) async fn fetch_properties(
+        &self,
+        client: &Client,
+    ) -> Result<CCashSessionProperties> {
         let request = client
             .get(format!("{}/properties", self.session_url))
             .header("Accept", "application/json")
@@ -212,23 +431,62 @@ impl CCashSession {
 
         let response = client.execute(request).await?;
 
-        if let Ok(v) = response.json::<CCashSessionProperties>().await {
-            self.properties = Some(v);
-            self.is_connected = true;
-            self.client = Some(client);
-            Ok(())
-        } else {
-            Err(CCashError::CouldNotParsePropertiesResponse)
+        response
+            .json::<CCashSessionProperties>()
+            .await
+            .map_err(|_| CCashError::CouldNotParsePropertiesResponse)
+    }
+
+    /// Returns the client of this `CCashSession` if it is connected, otherwise
+    /// builds a new client with its configuration.
+    pub(crate) fn client_or_build(&self) -> Result<Client> {
+        match self.get_client() {
+            Some(client) => Ok(client),
+            None => Ok(self.client_config.build()?),
         }
     }
 
     /// Gets the client associated with this instance of `CCashSession`
     #[must_use]
-    pub fn get_client(&self) -> &Option<Client> { &self.client }
+    pub fn get_client(&self) -> Option<Client> { self.connection.read().client.clone() }
     /// Returns whether or not the `CCashSession` is connectd to the instance.
     #[must_use]
-    pub fn is_connected(&self) -> bool { self.is_connected }
-    /// Returns the properties of the `CCash` instance.
+    pub fn is_connected(&self) -> bool { self.connection.read().is_connected }
+    /// Returns the properties of the `CCash` instance. These are refreshed
+    /// whenever the connection is re-established.
     #[must_use]
-    pub fn get_properties(&self) -> &Option<CCashSessionProperties> { &self.properties }
+    pub fn get_properties(&self) -> Option<CCashSessionProperties> {
+        self.connection.read().properties.clone()
+    }
+    /// Returns whether the connected `CCash` instance supports the
+    /// `capability`, or `false` if the connection has not been established.
+    #[must_use]
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.connection
+            .read()
+            .properties
+            .as_ref()
+            .is_some_and(|p| p.supports(capability))
+    }
+    /// Returns the [`RetryPolicy`] used for requests made with this
+    /// `CCashSession`.
+    #[must_use]
+    pub fn get_retry_policy(&self) -> &RetryPolicy { &self.retry_policy }
+}
+
+/// Returns the URL of the API of the `CCash` instance at `base_url`, where the
+/// API is found at `api_prefix`.
+pub(crate) fn api_url(base_url: &str, api_prefix: &str) -> String {
+    let base_url = if base_url.ends_with('/') {
+        base_url.trim_end_matches('/')
+    } else {
+        base_url
+    };
+    let api_prefix = api_prefix.trim_matches('/');
+
+    if api_prefix.is_empty() {
+        base_url.into()
+    } else {
+        format!("{base_url}/{api_prefix}")
+    }
 }
@@ -2,12 +2,18 @@
 //! endpoint provided by the [`CCash`](https://github.com/EntireTwix/CCash) API.
 //! Non-admin functions can be found within [`methods`].
 //!
+//! Admin endpoints respond to credentials that don't belong to the admin
+//! account with [`CCashApiError::AdminRequired`].
+//!
 //! [`methods`]: crate::methods
 
+#[allow(unused_imports)]
 use crate::{
-    request::request, CCashError, CCashResponse, CCashSession, CCashUser, Result,
+    request::{request, Endpoint},
+    user::Credentials,
+    CCashApiError, CCashError, CCashResponse, CCashSession, CCashUser, Csh, Result,
+    Username,
 };
-use reqwest::Method;
 use velcro::hash_map;
 
 /// Returns a boolean whether or not the [`user`](CCashUser) is an admin
@@ -18,19 +24,15 @@ use velcro::hash_map;
 /// Will return a [`CCashError`] if the request fails or if the `CCash` instance
 /// returns an error code other than 401, as long as the
 /// `interpret_endpoint_errors_as_false` feature is disabled.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
 pub async fn verify_account(session: &CCashSession, user: &CCashUser) -> Result<bool> {
-    let url = format!("{}/v1/admin/verify_account", &session.session_url);
+    let endpoint = Endpoint::AdminVerifyAccount;
 
-    let r = request::<()>(Method::POST, session, &url, Some(user), None).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(true),
-        #[cfg(feature = "interpret_endpoint_errors_as_false")]
-        CCashResponse::Error { .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { code: 401, .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let r = request::<()>(session, endpoint, Some(user), None, &[]).await?;
+    r.into_bool(endpoint, Some(401))
 }
 
 /// Changes the password for the [`user`](CCashUser). This function modifies
@@ -43,15 +45,25 @@ pub async fn verify_account(session: &CCashSession, user: &CCashUser) -> Result<
 ///
 /// Will return a [`CCashError`] if the request fails or if the `CCash` instance
 /// returns an error code as long as the `interpret_endpoint_errors_as_false`
-/// feature is disabled.
+/// feature is disabled. Returns [`CCashApiError::AdminRequired`] if
+/// `admin_user` is not the admin account, or [`CCashApiError::UserNotFound`]
+/// if `user` does not exist.
 #[allow(clippy::missing_panics_doc)]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, user = %user.username)
+    )
+)]
 pub async fn change_password(
     session: &CCashSession,
     admin_user: &CCashUser,
     user: &mut CCashUser,
     new_password: &str,
 ) -> Result<bool> {
-    let url = format!("{}/v1/admin/user/change_password", &session.session_url);
+    let endpoint = Endpoint::AdminChangePassword;
 
     let new_user = CCashUser::new(&user.username.clone(), new_password);
 
@@ -62,24 +74,19 @@ pub async fn change_password(
     let new_user = new_user.unwrap();
 
     let r = request(
-        Method::PATCH,
         session,
-        &url,
+        endpoint,
         Some(admin_user),
-        Some(&new_user),
+        Some(&new_user.credentials()),
+        &[],
     )
     .await?;
-
-    match r {
-        CCashResponse::Success { .. } => {
-            *user = new_user;
-            Ok(true)
-        },
-        #[cfg(feature = "interpret_endpoint_errors_as_false")]
-        CCashResponse::Error { .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { .. } => Err(r.into()),
+    let changed = r.into_bool(endpoint, None)?;
+    if changed {
+        *user = new_user;
     }
+
+    Ok(changed)
 }
 
 /// Sets the balance of a user with the given `username` to the amount described
@@ -89,45 +96,65 @@ pub async fn change_password(
 /// # Errors
 ///
 /// Will return a [`CCashError`] if the request fails or if the `CCash` instance
-/// returns an error code.
+/// returns an error code, such as [`CCashApiError::AdminRequired`] if
+/// `admin_user` is not the admin account or [`CCashApiError::UserNotFound`] if
+/// the user with the `username` does not exist.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, username = %username)
+    )
+)]
 pub async fn set_balance(
     session: &CCashSession,
     admin_user: &CCashUser,
-    username: &str,
-    new_balance: u32,
+    username: &Username,
+    new_balance: impl Into<Csh>,
 ) -> Result<()> {
     #[derive(serde::Serialize)]
     struct SetBalanceData {
         name: String,
-        amount: u32,
+        amount: Csh,
     }
 
-    let url = format!("{}/v1/admin/set_balance", &session.session_url);
+    let endpoint = Endpoint::AdminSetBalance;
 
     let body = SetBalanceData {
-        name: username.into(),
-        amount: new_balance,
+        name: username.to_string(),
+        amount: new_balance.into(),
     };
 
-    let r = request(Method::PATCH, session, &url, Some(admin_user), Some(&body)).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(()),
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let r = request(session, endpoint, Some(admin_user), Some(&body), &[]).await?;
+    r.into_unit(endpoint)
 }
 
 /// Impacts the balance of user with the given `username` by the amount
-/// described by `amount`. This function requires [`admin_user`](CCashUser) to
-/// be the admin account for the `CCash` instance.
+/// described by `amount`, which is a signed delta such as one returned by
+/// [`Csh::to_delta`] or [`Csh::to_negative_delta`]. This function requires
+/// [`admin_user`](CCashUser) to be the admin account for the `CCash` instance.
 ///
 /// # Errors
 ///
 /// Will return a [`CCashError`] if the request fails or if the `CCash` instance
-/// returns an error code.
+/// returns an error code, such as [`CCashApiError::AdminRequired`] if
+/// `admin_user` is not the admin account, [`CCashApiError::UserNotFound`] if
+/// the user with the `username` does not exist or
+/// [`CCashApiError::InsufficientFunds`] if the impact would take the balance
+/// below zero.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, username = %username)
+    )
+)]
 pub async fn impact_balance(
     session: &CCashSession,
     admin_user: &CCashUser,
-    username: &str,
+    username: &Username,
     amount: i64,
 ) -> Result<()> {
     #[derive(serde::Serialize)]
@@ -136,18 +163,15 @@ pub async fn impact_balance(
         amount: i64,
     }
 
-    let url = format!("{}/v1/admin/impact_balance", &session.session_url);
+    let endpoint = Endpoint::AdminImpactBalance;
 
     let body = ImpactBalanceData {
-        name: username.into(),
+        name: username.to_string(),
         amount,
     };
 
-    let r = request(Method::POST, session, &url, Some(admin_user), Some(&body)).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(()),
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let r = request(session, endpoint, Some(admin_user), Some(&body), &[]).await?;
+    r.into_unit(endpoint)
 }
 
 /// Adds a [`user`](CCashUser) to the `CCash` session described by
@@ -158,37 +182,39 @@ pub async fn impact_balance(
 ///
 /// Will return [`CCashError`] if the request fails or if the `CCash` instance
 /// returns an error code other than a 409 as long as the
-/// `interpret_endpoint_errors_as_false` feature is disabled.
+/// `interpret_endpoint_errors_as_false` feature is disabled, such as
+/// [`CCashApiError::AdminRequired`] if `admin_user` is not the admin account or
+/// [`CCashApiError::InvalidName`] if the name of `new_user` is rejected.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, new_user = %new_user.username)
+    )
+)]
 pub async fn add_user(
     session: &CCashSession,
     admin_user: &CCashUser,
     new_user: &CCashUser,
-    amount: u32,
+    amount: impl Into<Csh>,
 ) -> Result<bool> {
     #[derive(serde::Serialize)]
-    struct AddUserData {
+    struct AddUserData<'a> {
         #[serde(flatten)]
-        user: CCashUser,
-        amount: u32,
+        user: Credentials<'a>,
+        amount: Csh,
     }
 
-    let url = format!("{}/v1/admin/user/register", &session.session_url);
+    let endpoint = Endpoint::AdminAddUser;
 
     let body = AddUserData {
-        user: new_user.clone(),
-        amount,
+        user: new_user.credentials(),
+        amount: amount.into(),
     };
 
-    let r = request(Method::POST, session, &url, Some(admin_user), Some(&body)).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(true),
-        #[cfg(feature = "interpret_endpoint_errors_as_false")]
-        CCashResponse::Error { .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { code: 409, .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let r = request(session, endpoint, Some(admin_user), Some(&body), &[]).await?;
+    r.into_bool(endpoint, Some(409))
 }
 
 /// Removes a user associated with the `username` on the `CCash` instance
@@ -198,20 +224,27 @@ pub async fn add_user(
 /// # Errors
 ///
 /// Will return [`CCashError`] if request fails or if the `CCash` instance
-/// returns an error code.
+/// returns an error code, such as [`CCashApiError::AdminRequired`] if
+/// `admin_user` is not the admin account or [`CCashApiError::UserNotFound`] if
+/// the user with the `username` does not exist.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(admin = %admin_user.username, username = %username)
+    )
+)]
 pub async fn delete_user(
     session: &CCashSession,
     admin_user: &CCashUser,
-    username: &str,
+    username: &Username,
 ) -> Result<()> {
-    let url = format!("{}/v1/admin/user/delete", &session.session_url);
-    let body = hash_map! { "name": username };
+    let endpoint = Endpoint::AdminDeleteUser;
+    let body = hash_map! { "name": username.as_str() };
 
-    let r = request(Method::DELETE, session, &url, Some(admin_user), Some(&body)).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(()),
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let r = request(session, endpoint, Some(admin_user), Some(&body), &[]).await?;
+    r.into_unit(endpoint)
 }
 
 /// Prunes users with less than `amount` in balance or users with transactions
@@ -222,24 +255,32 @@ pub async fn delete_user(
 ///
 /// Will return [`CCashError`] if the request fails (could be down to
 /// wrong/incorrect admin credientials) or if the `CCash` instance refuses to
-/// prune it's users for another reason.
+/// prune it's users for another reason. Wrong admin credentials are returned as
+/// [`CCashApiError::AdminRequired`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(admin = %admin_user.username))
+)]
 pub async fn prune_users(
     session: &CCashSession,
     admin_user: &CCashUser,
-    amount: u32,
+    amount: impl Into<Csh>,
     time: Option<i64>,
 ) -> Result<u64> {
     #[derive(serde::Serialize)]
     struct PruneUsersData {
-        amount: u32,
+        amount: Csh,
         time: Option<i64>,
     }
 
-    let url = format!("{}/v1/admin/prune_users", &session.session_url);
+    let endpoint = Endpoint::AdminPruneUsers;
 
-    let body = PruneUsersData { amount, time };
+    let body = PruneUsersData {
+        amount: amount.into(),
+        time,
+    };
 
-    let r = request(Method::POST, session, &url, Some(admin_user), Some(&body)).await?;
+    let r = request(session, endpoint, Some(admin_user), Some(&body), &[]).await?;
     match r {
         CCashResponse::Success { .. } =>
             if let Ok(amount) = r.convert_message::<u64>() {
@@ -249,7 +290,7 @@ pub async fn prune_users(
                     "Could not parse amount of users pruned into a valid u64".into(),
                 ))
             },
-        CCashResponse::Error { .. } => Err(r.into()),
+        CCashResponse::Error { .. } => Err(r.into_error(endpoint)),
     }
 }
 
@@ -262,19 +303,19 @@ pub async fn prune_users(
 ///
 /// Will return [`CCashError`] if the request fails (could be down to
 /// wrong/incorrect admin credientials) or if the `CCash` instance refuses to
-/// close for another reason.
+/// close for another reason. Wrong admin credentials are returned as
+/// [`CCashApiError::AdminRequired`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(admin = %admin_user.username))
+)]
 pub async fn close(session: &mut CCashSession, admin_user: &CCashUser) -> Result<()> {
-    let url = format!("{}/v1/admin/shutdown", &session.session_url);
+    let endpoint = Endpoint::AdminClose;
 
-    let r = request::<()>(Method::POST, session, &url, Some(admin_user), None).await?;
-    match r {
-        CCashResponse::Success { .. } => {
-            session.is_connected = false;
-            session.client = None;
-            session.properties = None;
+    let r = request::<()>(session, endpoint, Some(admin_user), None, &[]).await?;
+    r.into_unit(endpoint)?;
 
-            Ok(())
-        },
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    session.connection.write().clear();
+
+    Ok(())
 }
@@ -1,15 +1,21 @@
 //! This module contains all the non-admin functions that can be mapped to an
 //! endpoint provided by the [`CCash`](https://github.com/EntireTwix/CCash) API.
 //! All admin functions can be found within the [`admin`] module.
+//!
+//! Errors returned by the `CCash` instance are surfaced as
+//! [`CCashError::ApiError`], holding the [`CCashApiError`] that describes the
+//! failure for the endpoint that was called.
 
 pub mod admin;
 
 #[allow(unused_imports)]
 use crate::{
-    request::request, CCashError, CCashResponse, CCashSession, CCashUser, Result,
-    TransactionLog, TransactionLogV2,
+    history::BalanceHistory,
+    query::LogQuery,
+    request::{request, Endpoint},
+    CCashApiError, CCashError, CCashResponse, CCashSession, CCashSessionProperties,
+    CCashUser, Capability, Csh, Result, TransactionLog, TransactionLogV2,
 };
-use reqwest::Method;
 use velcro::hash_map;
 
 /// Returns the balance of the [`user`](CCashUser).
@@ -17,19 +23,24 @@ use velcro::hash_map;
 /// # Errors
 ///
 /// Will return [`CCashError`] if the request fails or if the response from
-/// `CCash` cannot be parsed as a valid `u32`.
-pub async fn get_balance(session: &CCashSession, user: &CCashUser) -> Result<u32> {
-    let url = format!(
-        "{}/v1/user/balance?name={}",
-        &session.session_url, &user.username
-    );
-
-    let r = request::<()>(Method::GET, session, &url, Some(user), None).await?;
-    if let Ok(v) = r.convert_message::<u32>() {
-        Ok(v)
-    } else {
-        Err(r.into())
-    }
+/// `CCash` cannot be parsed as a valid [`Csh`]. Returns
+/// [`CCashApiError::UserNotFound`] if the `user` does not exist.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub async fn get_balance(session: &CCashSession, user: &CCashUser) -> Result<Csh> {
+    let endpoint = Endpoint::GetBalance;
+
+    let r = request::<()>(
+        session,
+        endpoint,
+        Some(user),
+        None,
+        &[("name", &user.username)],
+    )
+    .await?;
+    r.into_value(endpoint)
 }
 
 /// Returns the transaction logs for a given [`user`](CCashUser). This function
@@ -38,20 +49,22 @@ pub async fn get_balance(session: &CCashSession, user: &CCashUser) -> Result<u32
 /// # Errors
 ///
 /// Will return a [`CCashError`] if the request fails or if the data returned by
-/// `CCash` cannot be parsed into a valid `Vec<TransactionLog>`.
+/// `CCash` cannot be parsed into a valid `Vec<TransactionLog>`. Returns
+/// [`CCashApiError::InvalidCredentials`] if the password of the `user` is
+/// incorrect.
 #[deprecated(since = "2.0.0", note = "Please use `get_log_v2` where possible")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
 pub async fn get_log(
     session: &CCashSession,
     user: &CCashUser,
 ) -> Result<Vec<TransactionLog>> {
-    let url = format!("{}/v1/user/log", &session.session_url);
+    let endpoint = Endpoint::GetLog;
 
-    let r = request::<()>(Method::GET, session, &url, Some(user), None).await?;
-    if let Ok(v) = r.convert_message::<Vec<TransactionLog>>() {
-        Ok(v)
-    } else {
-        Err(r.into())
-    }
+    let r = request::<()>(session, endpoint, Some(user), None, &[]).await?;
+    r.into_value(endpoint)
 }
 
 /// Returns the transaction logs for a given [`user`](CCashUser). This function
@@ -60,18 +73,123 @@ pub async fn get_log(
 /// # Errors
 ///
 /// Will return a [`CCashError`] if the request fails or if the data returned by
-/// `CCash` cannot be parsed into a valid `Vec<`[`TransactionLogV2`]`>`.
+/// `CCash` cannot be parsed into a valid `Vec<`[`TransactionLogV2`]`>`. Returns
+/// [`CCashApiError::InvalidCredentials`] if the password of the `user` is
+/// incorrect, or [`CCashError::UnsupportedByServer`] if the `CCash` instance
+/// reports a version older than 2.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
 pub async fn get_log_v2(
     session: &CCashSession,
     user: &CCashUser,
 ) -> Result<Vec<TransactionLogV2>> {
-    let url = format!("{}/v2/user/log", &session.session_url);
+    let endpoint = Endpoint::GetLogV2;
+
+    let r = request::<()>(session, endpoint, Some(user), None, &[]).await?;
+    r.into_value(endpoint)
+}
+
+/// Returns the transaction logs for a given [`user`](CCashUser) from the best
+/// endpoint supported by the `CCash` instance. Instances that don't support
+/// [`get_log_v2`], or that don't report their version and don't have its
+/// endpoint, are called with [`get_log`], and the logs are converted with
+/// [`TransactionLog::to_v2`]. This function requires a correct password.
+///
+/// # Errors
+///
+/// Will return a [`CCashError`] for the same reasons as [`get_log_v2`], other
+/// than [`CCashError::UnsupportedByServer`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub async fn get_logs(
+    session: &CCashSession,
+    user: &CCashUser,
+) -> Result<Vec<TransactionLogV2>> {
+    if session.supports(Capability::LogV2) {
+        match get_log_v2(session, user).await {
+            // Instances that don't report their version may be older than
+            // version 2, in which case the v2 endpoint is not found.
+            Err(CCashError::ApiError(CCashApiError::UserNotFound { .. }))
+                if session
+                    .get_properties()
+                    .is_some_and(|p| p.get_version().is_none()) => {},
+            result => return result,
+        }
+    }
+
+    let logs = get_log(session, user).await?;
+    Ok(logs.iter().map(|log| log.to_v2(&user.username)).collect())
+}
+
+/// Returns the transaction logs for a given [`user`](CCashUser) that match the
+/// `query`, sorted and limited as described by the `query`. This function
+/// requires a correct password.
+///
+/// # Errors
+///
+/// Will return a [`CCashError`] for the same reasons as [`get_logs`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub async fn get_log_v2_filtered(
+    session: &CCashSession,
+    user: &CCashUser,
+    query: &LogQuery,
+) -> Result<Vec<TransactionLogV2>> {
+    let logs = get_logs(session, user).await?;
+    Ok(query.apply(logs))
+}
+
+/// The max amount of times [`get_balance_history`] fetches the logs of a user
+/// while their balance keeps changing.
+pub(crate) const BALANCE_HISTORY_ATTEMPTS: u32 = 3;
+
+/// Returns the [`BalanceHistory`] of a given [`user`](CCashUser), reconstructed
+/// from their balance and transaction logs. This function requires a correct
+/// password. If the session has no properties, the history is assumed to be
+/// truncated.
+///
+/// The balance is fetched before and after the logs, and the logs are fetched
+/// again if it changed in between. If the balance keeps changing, the history
+/// is built from the last logs and balance fetched, and is flagged as not
+/// [`consistent`](BalanceHistory::is_consistent).
+///
+/// # Errors
+///
+/// Will return a [`CCashError`] for the same reasons as [`get_balance`] and
+/// [`get_logs`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
+pub async fn get_balance_history(
+    session: &CCashSession,
+    user: &CCashUser,
+) -> Result<BalanceHistory> {
+    let max_log = session
+        .get_properties()
+        .as_ref()
+        .map(CCashSessionProperties::get_max_log);
+
+    let mut balance = get_balance(session, user).await?;
+    let mut attempt = 1;
+    loop {
+        let logs = get_logs(session, user).await?;
+        let balance_after = get_balance(session, user).await?;
 
-    let r = request::<()>(Method::GET, session, &url, Some(user), None).await?;
-    if let Ok(v) = r.convert_message::<Vec<TransactionLogV2>>() {
-        Ok(v)
-    } else {
-        Err(r.into())
+        if balance_after == balance || attempt >= BALANCE_HISTORY_ATTEMPTS {
+            let mut history = BalanceHistory::from_logs(balance_after, logs, max_log);
+            history.is_consistent = balance_after == balance;
+            return Ok(history);
+        }
+
+        balance = balance_after;
+        attempt += 1;
     }
 }
 
@@ -83,22 +201,22 @@ pub async fn get_log_v2(
 /// Will return a [`CCashError`] if the request fails or if the `CCash` instance
 /// returns an error code as long as the error code isn't a 401 and as long as
 /// the `interpret_endpoint_errors_as_false` is disabled.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
 pub async fn contains_user(session: &CCashSession, user: &CCashUser) -> Result<bool> {
-    let url = format!(
-        "{}/v1/user/exists?name={}",
-        &session.session_url, &user.username
-    );
-
-    let r = request::<()>(Method::GET, session, &url, Some(user), None).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(true),
-        #[cfg(feature = "interpret_endpoint_errors_as_false")]
-        CCashResponse::Error { .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { code: 401, .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let endpoint = Endpoint::ContainsUser;
+
+    let r = request::<()>(
+        session,
+        endpoint,
+        Some(user),
+        None,
+        &[("name", &user.username)],
+    )
+    .await?;
+    r.into_bool(endpoint, Some(401))
 }
 
 /// Returns a `bool` about whether or not the `password` for a
@@ -108,20 +226,17 @@ pub async fn contains_user(session: &CCashSession, user: &CCashUser) -> Result<b
 ///
 /// Will return a [`CCashError`] if the request fails or if the `CCash` instance
 /// returns an error code when verifing the password as long as the
-/// `interpret_endpoint_errors_as_false` feature is disabled.
+/// `interpret_endpoint_errors_as_false` feature is disabled. An incorrect
+/// password is not an error, and is returned as `Ok(false)`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
 pub async fn verify_password(session: &CCashSession, user: &CCashUser) -> Result<bool> {
-    let url = format!("{}/v1/user/verify_password", &session.session_url);
-
-    let r = request::<()>(Method::POST, session, &url, Some(user), None).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(true),
-        #[cfg(feature = "interpret_endpoint_errors_as_false")]
-        CCashResponse::Error { .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { code: 401, .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let endpoint = Endpoint::VerifyPassword;
+
+    let r = request::<()>(session, endpoint, Some(user), None, &[]).await?;
+    r.into_bool(endpoint, Some(401))
 }
 
 /// Returns `true` about if a password change was successful for the given
@@ -133,26 +248,28 @@ pub async fn verify_password(session: &CCashSession, user: &CCashUser) -> Result
 ///
 /// Will return a [`CCashError`] if the request fails or if `CCash` instance
 /// returns an error code when changing the password for the `user`, as long as
-/// the `interpret_endpoint_errors_as_false` feature is disabled.
+/// the `interpret_endpoint_errors_as_false` feature is disabled. Returns
+/// [`CCashApiError::InvalidCredentials`] if the current password of the `user`
+/// is incorrect.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
 pub async fn change_password(
     session: &CCashSession,
     user: &mut CCashUser,
     new_password: &str,
 ) -> Result<bool> {
-    let url = format!("{}/v1/user/change_password", &session.session_url);
+    let endpoint = Endpoint::ChangePassword;
     let body = hash_map! { "pass": new_password };
 
-    let r = request(Method::PATCH, session, &url, Some(user), Some(&body)).await?;
-    match r {
-        CCashResponse::Success { .. } => {
-            user.update_password(new_password);
-            Ok(true)
-        },
-        #[cfg(feature = "interpret_endpoint_errors_as_false")]
-        CCashResponse::Error { .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { .. } => Err(r.into()),
+    let r = request(session, endpoint, Some(user), Some(&body), &[]).await?;
+    let changed = r.into_bool(endpoint, None)?;
+    if changed {
+        user.update_password(new_password);
     }
+
+    Ok(changed)
 }
 
 /// Sends funds from the [`user`](CCashUser) to the user with the
@@ -162,32 +279,42 @@ pub async fn change_password(
 /// # Errors
 ///
 /// Will return a [`CCashError`] if the request fails or if the message returned
-/// back by `CCash` cannot be parsed into a `u32`.
+/// back by `CCash` cannot be parsed into a [`Csh`]. Notably:
+/// - [`CCashApiError::InsufficientFunds`] if the `user` cannot afford to send
+///   `amount`.
+/// - [`CCashApiError::UserNotFound`] if the recipient does not exist.
+/// - [`CCashApiError::InvalidCredentials`] if the password of the `user` is
+///   incorrect.
+/// - [`CCashApiError::InvalidRequest`] if the transfer is otherwise rejected,
+///   such as when sending funds to oneself.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "info",
+        skip_all,
+        fields(user = %user.username, recipient = recipient_name)
+    )
+)]
 pub async fn send_funds(
     session: &CCashSession,
     user: &CCashUser,
     recipient_name: &str,
-    amount: u32,
-) -> Result<u32> {
+    amount: impl Into<Csh>,
+) -> Result<Csh> {
     #[derive(serde::Serialize)]
     struct FundsTransfer {
         name: String,
-        amount: u32,
+        amount: Csh,
     }
 
-    let url = format!("{}/v1/user/transfer", &session.session_url);
+    let endpoint = Endpoint::SendFunds;
     let body = FundsTransfer {
         name: recipient_name.into(),
-        amount,
+        amount: amount.into(),
     };
 
-    let r = request(Method::POST, session, &url, Some(user), Some(&body)).await?;
-
-    if let Ok(v) = r.convert_message::<u32>() {
-        Ok(v)
-    } else {
-        Err(r.into())
-    }
+    let r = request(session, endpoint, Some(user), Some(&body), &[]).await?;
+    r.into_value(endpoint)
 }
 
 /// Adds a [`user`](CCashUser) with a balance of 0.
@@ -202,20 +329,18 @@ pub async fn send_funds(
 ///
 /// Will return [`CCashError`] if the instance returns an error response
 /// (other than a 409) *and* the feature `interpret_endpoint_errors_as_false` is
-/// disabled.
+/// disabled. Returns [`CCashApiError::InvalidName`] if the `CCash` instance
+/// rejects the username, or [`CCashError::UnsupportedByServer`] if the `CCash`
+/// instance has closed registration to users without the admin account.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
 pub async fn add_user(session: &CCashSession, user: &CCashUser) -> Result<bool> {
-    let url = format!("{}/v1/user/register", &session.session_url);
-
-    let r = request(Method::POST, session, &url, None, Some(user)).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(true),
-        #[cfg(feature = "interpret_endpoint_errors_as_false")]
-        CCashResponse::Error { .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { code: 409, .. } => Ok(false),
-        #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let endpoint = Endpoint::AddUser;
+
+    let r = request(session, endpoint, None, Some(&user.credentials()), &[]).await?;
+    r.into_bool(endpoint, Some(409))
 }
 
 /// Removes the [`user`](CCashUser). This function requires the
@@ -224,13 +349,16 @@ pub async fn add_user(session: &CCashSession, user: &CCashUser) -> Result<bool>
 ///
 /// # Errors
 ///
-/// Will return [`CCashError`] if request fails.
+/// Will return [`CCashError`] if request fails. Returns
+/// [`CCashApiError::InvalidCredentials`] if the password of the `user` is
+/// incorrect.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, fields(user = %user.username))
+)]
 pub async fn delete_user(session: &CCashSession, user: &CCashUser) -> Result<()> {
-    let url = format!("{}/v1/user/delete", &session.session_url);
+    let endpoint = Endpoint::DeleteUser;
 
-    let r = request::<()>(Method::DELETE, session, &url, Some(user), None).await?;
-    match r {
-        CCashResponse::Success { .. } => Ok(()),
-        CCashResponse::Error { .. } => Err(r.into()),
-    }
+    let r = request::<()>(session, endpoint, Some(user), None, &[]).await?;
+    r.into_unit(endpoint)
 }
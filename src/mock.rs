@@ -0,0 +1,787 @@
+//! This module contains an in-process mock of a `CCash` instance, so that code
+//! using `ccash-rs` can be tested without running `CCash` itself. It requires
+//! the `test-util` feature to be enabled, and must be used from within a
+//! `tokio` runtime.
+//!
+//! The [`MockServer`] binds a local port and implements every endpoint called
+//! by [`methods`](crate::methods) and [`methods::admin`](crate::methods::admin)
+//! with the same status codes as `CCash`, including basic authentication,
+//! truncation of logs to `max_log` entries and returning the funds of deleted
+//! users to the `return_on_del` account.
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> ccash_rs::Result<()> {
+//! use ccash_rs::{mock::MockServer, *};
+//!
+//! let mock = MockServer::start().await;
+//! mock.add_user("alice", "password", 100);
+//! mock.add_user("bob", "password", 0);
+//!
+//! let mut session = CCashSession::new(&mock.url());
+//! session.establish_connection().await?;
+//!
+//! let alice = CCashUser::new("alice", "password")?;
+//! assert_eq!(methods::send_funds(&session, &alice, "bob", 25).await?, 75);
+//! assert_eq!(mock.balance("bob"), Some(Csh::new(25)));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{CCashUser, Csh, TransactionLogV2};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::sync::Notify;
+
+/// Struct that describes how the [`MockServer`] behaves, mirroring the options
+/// a `CCash` instance is compiled with.
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// The name of the admin account.
+    pub admin_name: String,
+    /// The password of the admin account.
+    pub admin_password: String,
+    /// The version reported by the `properties` endpoint.
+    pub version: Option<u32>,
+    /// The max amount of logs kept for each user.
+    pub max_log: u32,
+    /// Whether any user can register without an admin account.
+    pub add_user_open: bool,
+    /// The account that funds are returned to when a user is deleted.
+    pub return_on_del: Option<String>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            admin_name: "admin".into(),
+            admin_password: "admin".into(),
+            version: Some(2),
+            max_log: 100,
+            add_user_open: true,
+            return_on_del: None,
+        }
+    }
+}
+
+/// A failure the [`MockServer`] injects into a request it receives, to test how
+/// callers handle errors and lost responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFault {
+    /// Responds with the given status code without handling the request.
+    Reject(u16),
+    /// Handles the request, but responds with the given status code instead,
+    /// as if the response had been lost on its way to the client.
+    LoseResponse(u16),
+}
+
+#[derive(Debug, Clone)]
+struct MockLogEntry {
+    counterparty: String,
+    receiving: bool,
+    amount: u32,
+    time: i64,
+}
+
+#[derive(Debug, Clone)]
+struct MockUser {
+    password: String,
+    balance: u32,
+    log: Vec<MockLogEntry>,
+}
+
+impl MockUser {
+    fn new(password: &str, balance: u32) -> Self {
+        Self {
+            password: password.into(),
+            balance,
+            log: Vec::new(),
+        }
+    }
+
+    fn push_log(&mut self, entry: MockLogEntry, max_log: u32) {
+        self.log.push(entry);
+
+        let max_log = max_log as usize;
+        if self.log.len() > max_log {
+            self.log.drain(..self.log.len() - max_log);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MockState {
+    config: MockConfig,
+    users: BTreeMap<String, MockUser>,
+    faults: Vec<(String, MockFault)>,
+    requests: usize,
+    is_shut_down: bool,
+}
+
+/// A mock `CCash` instance running on a local port. The server stops when this
+/// handle is dropped or when the `shutdown` endpoint is called.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<Notify>,
+}
+
+impl MockServer {
+    /// Starts a new `MockServer` with the default [`MockConfig`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a local port could not be bound.
+    pub async fn start() -> MockServer { Self::start_with(MockConfig::default()).await }
+
+    /// Starts a new `MockServer` with the given `config`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a local port could not be bound.
+    #[allow(clippy::unused_async)]
+    pub async fn start_with(config: MockConfig) -> MockServer {
+        let mut users = BTreeMap::new();
+        users.insert(
+            config.admin_name.clone(),
+            MockUser::new(&config.admin_password, 0),
+        );
+
+        let state = Arc::new(Mutex::new(MockState {
+            config,
+            users,
+            faults: Vec::new(),
+            requests: 0,
+            is_shut_down: false,
+        }));
+        let shutdown = Arc::new(Notify::new());
+
+        let listener =
+            TcpListener::bind("127.0.0.1:0").expect("could not bind a local port");
+        listener
+            .set_nonblocking(true)
+            .expect("could not set the listener to non-blocking");
+        let addr = listener
+            .local_addr()
+            .expect("could not get the local address");
+
+        let service_state = Arc::clone(&state);
+        let service_shutdown = Arc::clone(&shutdown);
+        let make_service = make_service_fn(move |_| {
+            let state = Arc::clone(&service_state);
+            let shutdown = Arc::clone(&service_shutdown);
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle(Arc::clone(&state), Arc::clone(&shutdown), request)
+                }))
+            }
+        });
+
+        let server = Server::from_tcp(listener)
+            .expect("could not start the mock server")
+            .serve(make_service);
+        let signal = Arc::clone(&shutdown);
+        tokio::spawn(server.with_graceful_shutdown(async move {
+            signal.notified().await;
+        }));
+
+        MockServer {
+            addr,
+            state,
+            shutdown,
+        }
+    }
+
+    /// Returns the base URL of the mock server, to be passed to
+    /// [`CCashSession::new`](crate::CCashSession::new).
+    #[must_use]
+    pub fn url(&self) -> String { format!("http://{}", self.addr) }
+
+    /// Returns the [`CCashUser`] of the admin account.
+    #[must_use]
+    pub fn admin(&self) -> CCashUser {
+        let state = self.state();
+        CCashUser::new_unchecked(&state.config.admin_name, &state.config.admin_password)
+    }
+
+    /// Adds a user with the given `balance`, replacing any existing user with
+    /// the same `name`.
+    pub fn add_user(&self, name: &str, password: &str, balance: impl Into<Csh>) {
+        self.state()
+            .users
+            .insert(name.into(), MockUser::new(password, balance.into().get()));
+    }
+
+    /// Removes the user with the given `name`, returning whether it existed.
+    #[allow(clippy::must_use_candidate)]
+    pub fn remove_user(&self, name: &str) -> bool {
+        self.state().users.remove(name).is_some()
+    }
+
+    /// Sets the balance of the user with the given `name`, returning whether
+    /// the user exists.
+    #[allow(clippy::must_use_candidate)]
+    pub fn set_balance(&self, name: &str, balance: impl Into<Csh>) -> bool {
+        if let Some(user) = self.state().users.get_mut(name) {
+            user.balance = balance.into().get();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Appends a log entry for the user with the given `name`, as if a
+    /// transaction with `counterparty` had happened at `time`. This does not
+    /// modify any balances.
+    #[allow(clippy::must_use_candidate)]
+    pub fn push_log(
+        &self,
+        name: &str,
+        counterparty: &str,
+        receiving: bool,
+        amount: impl Into<Csh>,
+        time: i64,
+    ) -> bool {
+        let mut state = self.state();
+        let max_log = state.config.max_log;
+        if let Some(user) = state.users.get_mut(name) {
+            user.push_log(
+                MockLogEntry {
+                    counterparty: counterparty.into(),
+                    receiving,
+                    amount: amount.into().get(),
+                    time,
+                },
+                max_log,
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns whether a user with the given `name` exists.
+    #[must_use]
+    pub fn contains_user(&self, name: &str) -> bool {
+        self.state().users.contains_key(name)
+    }
+
+    /// Returns the names of all users, including the admin account.
+    #[must_use]
+    pub fn usernames(&self) -> Vec<String> {
+        self.state().users.keys().cloned().collect()
+    }
+
+    /// Returns the balance of the user with the given `name`.
+    #[must_use]
+    pub fn balance(&self, name: &str) -> Option<Csh> {
+        self.state().users.get(name).map(|u| Csh::new(u.balance))
+    }
+
+    /// Returns the password of the user with the given `name`.
+    #[must_use]
+    pub fn password(&self, name: &str) -> Option<String> {
+        self.state().users.get(name).map(|u| u.password.clone())
+    }
+
+    /// Returns the logs of the user with the given `name`, newest first.
+    #[must_use]
+    pub fn log(&self, name: &str) -> Option<Vec<TransactionLogV2>> {
+        self.state().users.get(name).map(log_v2)
+    }
+
+    /// Queues `fault` to be injected into the next request to `path` (such as
+    /// `/v1/user/transfer`) that has no fault queued before it.
+    pub fn inject_fault(&self, path: &str, fault: MockFault) {
+        self.state().faults.push((path.into(), fault));
+    }
+
+    /// Returns the number of requests received so far, including those that
+    /// a [`MockFault`] was injected into.
+    #[must_use]
+    pub fn request_count(&self) -> usize { self.state().requests }
+
+    /// Returns whether the `shutdown` endpoint has been called.
+    #[must_use]
+    pub fn is_shut_down(&self) -> bool { self.state().is_shut_down }
+
+    fn state(&self) -> MutexGuard<'_, MockState> { lock(&self.state) }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) { self.shutdown.notify_one(); }
+}
+
+fn lock(state: &Mutex<MockState>) -> MutexGuard<'_, MockState> {
+    state
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn log_v2(user: &MockUser) -> Vec<TransactionLogV2> {
+    user.log
+        .iter()
+        .rev()
+        .map(|e| TransactionLogV2 {
+            counterparty: e.counterparty.clone(),
+            receiving: e.receiving,
+            amount: Csh::new(e.amount),
+            time: e.time,
+        })
+        .collect()
+}
+
+fn log_v2_json(user: &MockUser) -> serde_json::Value {
+    log_v2(user)
+        .iter()
+        .map(|e| {
+            serde_json::json!({
+                "counterparty": e.counterparty,
+                "receiving": e.receiving,
+                "amount": e.amount,
+                "time": e.time,
+            })
+        })
+        .collect()
+}
+
+fn log_v1_json(name: &str, user: &MockUser) -> serde_json::Value {
+    user.log
+        .iter()
+        .rev()
+        .map(|e| {
+            let (to, from) = if e.receiving {
+                (name, e.counterparty.as_str())
+            } else {
+                (e.counterparty.as_str(), name)
+            };
+
+            serde_json::json!({
+                "to": to,
+                "from": from,
+                "amount": e.amount,
+                "time": e.time,
+            })
+        })
+        .collect()
+}
+
+fn is_valid_name(name: &str) -> bool {
+    (3..=16).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn respond(code: StatusCode, body: &impl Serialize) -> Response<Body> {
+    Response::builder()
+        .status(code)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(body).unwrap_or_default()))
+        .unwrap()
+}
+
+fn empty(code: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(code)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn error(code: StatusCode, message: &str) -> Response<Body> { respond(code, &message) }
+
+fn credentials(request: &Request<Body>) -> Option<(String, String)> {
+    let header = request
+        .headers()
+        .get(hyper::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?;
+    let decoded = STANDARD.decode(header.strip_prefix("Basic ")?).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (name, password) = decoded.split_once(':')?;
+
+    Some((name.into(), password.into()))
+}
+
+fn query_name(request: &Request<Body>) -> Option<String> {
+    let url = Url::parse(&format!("http://localhost{}", request.uri())).ok()?;
+    let name = url
+        .query_pairs()
+        .find(|(k, _)| k == "name")
+        .map(|(_, v)| v.into_owned());
+    name
+}
+
+async fn body<T: DeserializeOwned>(request: Request<Body>) -> Option<T> {
+    let bytes = hyper::body::to_bytes(request.into_body()).await.ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Returns the name of the user authenticated by the `request`, or `None` if
+/// the credentials are missing or incorrect.
+fn authenticate(state: &MockState, request: &Request<Body>) -> Option<String> {
+    let (name, password) = credentials(request)?;
+    let user = state.users.get(&name)?;
+
+    (user.password == password).then_some(name)
+}
+
+fn is_admin(state: &MockState, request: &Request<Body>) -> bool {
+    authenticate(state, request).as_deref() == Some(state.config.admin_name.as_str())
+}
+
+fn delete(state: &mut MockState, name: &str) {
+    let Some(user) = state.users.remove(name) else {
+        return;
+    };
+
+    if let Some(return_on_del) = state.config.return_on_del.clone() {
+        if let Some(account) = state.users.get_mut(&return_on_del) {
+            account.balance = account.balance.saturating_add(user.balance);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NameBody {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct PassBody {
+    pass: String,
+}
+
+#[derive(Deserialize)]
+struct UserBody {
+    name: String,
+    pass: String,
+}
+
+#[derive(Deserialize)]
+struct AmountBody<T> {
+    name: String,
+    amount: T,
+}
+
+#[derive(Deserialize)]
+struct AddUserBody {
+    name: String,
+    pass: String,
+    amount: u32,
+}
+
+#[derive(Deserialize)]
+struct PruneBody {
+    amount: u32,
+    time: Option<i64>,
+}
+
+async fn handle(
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<Notify>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let fault = {
+        let mut state = lock(&state);
+        state.requests += 1;
+
+        let path = request.uri().path();
+        let path = path.strip_prefix("/api").unwrap_or(path);
+        let index = state.faults.iter().position(|(p, _)| p == path);
+        index.map(|i| state.faults.remove(i).1)
+    };
+
+    match fault {
+        Some(MockFault::Reject(code)) => Ok(empty(status(code))),
+        Some(MockFault::LoseResponse(code)) => {
+            let _ = route(state, shutdown, request).await;
+            Ok(empty(status(code)))
+        },
+        None => route(state, shutdown, request).await,
+    }
+}
+
+fn status(code: u16) -> StatusCode {
+    StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[allow(clippy::too_many_lines)]
+async fn route(
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<Notify>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let path = path.strip_prefix("/api").unwrap_or(&path);
+    let (auth, admin, add_user_open) = {
+        let state = lock(&state);
+        (
+            authenticate(&state, &request),
+            is_admin(&state, &request),
+            state.config.add_user_open,
+        )
+    };
+
+    let is_admin_path = path.starts_with("/v1/admin/");
+    if is_admin_path && !admin {
+        return Ok(error(StatusCode::UNAUTHORIZED, "Invalid Credentials"));
+    }
+
+    let response = match (method, path) {
+        (Method::GET, "/properties") => {
+            let state = lock(&state);
+            respond(
+                StatusCode::OK,
+                &serde_json::json!({
+                    "version": state.config.version,
+                    "max_log": state.config.max_log,
+                    "add_user_open": state.config.add_user_open,
+                    "return_on_del": state.config.return_on_del,
+                }),
+            )
+        },
+        (Method::GET, "/v1/user/balance") => {
+            let state = lock(&state);
+            match query_name(&request).and_then(|n| state.users.get(&n)) {
+                Some(user) => respond(StatusCode::OK, &user.balance),
+                None => error(StatusCode::NOT_FOUND, "User not found"),
+            }
+        },
+        (Method::GET, "/v1/user/exists") => {
+            let state = lock(&state);
+            match query_name(&request).and_then(|n| state.users.get(&n)) {
+                Some(_) => empty(StatusCode::NO_CONTENT),
+                None => error(StatusCode::NOT_FOUND, "User not found"),
+            }
+        },
+        (Method::GET, "/v1/user/log") => match auth {
+            Some(name) => {
+                let state = lock(&state);
+                respond(StatusCode::OK, &log_v1_json(&name, &state.users[&name]))
+            },
+            None => error(StatusCode::UNAUTHORIZED, "Invalid Credentials"),
+        },
+        (Method::GET, "/v2/user/log") => match auth {
+            Some(name) => {
+                let state = lock(&state);
+                respond(StatusCode::OK, &log_v2_json(&state.users[&name]))
+            },
+            None => error(StatusCode::UNAUTHORIZED, "Invalid Credentials"),
+        },
+        (Method::POST, "/v1/user/verify_password") => match auth {
+            Some(_) => empty(StatusCode::NO_CONTENT),
+            None => error(StatusCode::UNAUTHORIZED, "Invalid Credentials"),
+        },
+        (Method::PATCH, "/v1/user/change_password") => match auth {
+            Some(name) => match body::<PassBody>(request).await {
+                Some(body) => {
+                    let mut state = lock(&state);
+                    if let Some(user) = state.users.get_mut(&name) {
+                        user.password = body.pass;
+                    }
+                    empty(StatusCode::NO_CONTENT)
+                },
+                None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+            },
+            None => error(StatusCode::UNAUTHORIZED, "Invalid Credentials"),
+        },
+        (Method::POST, "/v1/user/transfer") => match auth {
+            Some(name) => match body::<AmountBody<u32>>(request).await {
+                Some(body) => transfer(&mut lock(&state), &name, &body.name, body.amount),
+                None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+            },
+            None => error(StatusCode::UNAUTHORIZED, "Invalid Credentials"),
+        },
+        (Method::POST, "/v1/user/register") if add_user_open =>
+            match body::<UserBody>(request).await {
+                Some(body) => register(&mut lock(&state), &body.name, &body.pass, 0),
+                None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+            },
+        (Method::DELETE, "/v1/user/delete") => match auth {
+            Some(name) => {
+                delete(&mut lock(&state), &name);
+                empty(StatusCode::NO_CONTENT)
+            },
+            None => error(StatusCode::UNAUTHORIZED, "Invalid Credentials"),
+        },
+        (Method::POST, "/v1/admin/verify_account") => empty(StatusCode::NO_CONTENT),
+        (Method::PATCH, "/v1/admin/user/change_password") =>
+            match body::<UserBody>(request).await {
+                Some(body) => match lock(&state).users.get_mut(&body.name) {
+                    Some(user) => {
+                        user.password = body.pass;
+                        empty(StatusCode::NO_CONTENT)
+                    },
+                    None => error(StatusCode::NOT_FOUND, "User not found"),
+                },
+                None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+            },
+        (Method::PATCH, "/v1/admin/set_balance") =>
+            match body::<AmountBody<u32>>(request).await {
+                Some(body) => match lock(&state).users.get_mut(&body.name) {
+                    Some(user) => {
+                        user.balance = body.amount;
+                        empty(StatusCode::NO_CONTENT)
+                    },
+                    None => error(StatusCode::NOT_FOUND, "User not found"),
+                },
+                None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+            },
+        (Method::POST, "/v1/admin/impact_balance") =>
+            match body::<AmountBody<i64>>(request).await {
+                Some(body) => match lock(&state).users.get_mut(&body.name) {
+                    Some(user) =>
+                        match u32::try_from(i64::from(user.balance) + body.amount) {
+                            Ok(balance) => {
+                                user.balance = balance;
+                                empty(StatusCode::NO_CONTENT)
+                            },
+                            Err(_) =>
+                                error(StatusCode::BAD_REQUEST, "Insufficient funds"),
+                        },
+                    None => error(StatusCode::NOT_FOUND, "User not found"),
+                },
+                None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+            },
+        (Method::POST, "/v1/admin/user/register") =>
+            match body::<AddUserBody>(request).await {
+                Some(body) =>
+                    register(&mut lock(&state), &body.name, &body.pass, body.amount),
+                None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+            },
+        (Method::DELETE, "/v1/admin/user/delete") =>
+            match body::<NameBody>(request).await {
+                Some(body) => {
+                    let mut state = lock(&state);
+                    if state.users.contains_key(&body.name) {
+                        delete(&mut state, &body.name);
+                        empty(StatusCode::NO_CONTENT)
+                    } else {
+                        error(StatusCode::NOT_FOUND, "User not found")
+                    }
+                },
+                None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+            },
+        (Method::POST, "/v1/admin/prune_users") => match body::<PruneBody>(request).await
+        {
+            Some(body) => respond(StatusCode::OK, &prune(&mut lock(&state), &body)),
+            None => error(StatusCode::BAD_REQUEST, "Invalid request body"),
+        },
+        (Method::POST, "/v1/admin/shutdown") => {
+            lock(&state).is_shut_down = true;
+            shutdown.notify_one();
+            empty(StatusCode::NO_CONTENT)
+        },
+        _ => empty(StatusCode::NOT_FOUND),
+    };
+
+    Ok(response)
+}
+
+fn transfer(
+    state: &mut MockState,
+    sender: &str,
+    recipient: &str,
+    amount: u32,
+) -> Response<Body> {
+    if sender == recipient {
+        return error(StatusCode::BAD_REQUEST, "Sender is Reciever");
+    }
+    if !state.users.contains_key(recipient) {
+        return error(StatusCode::NOT_FOUND, "Reciever does not exist");
+    }
+    if state.users[sender].balance < amount {
+        return error(StatusCode::BAD_REQUEST, "Insufficient funds");
+    }
+
+    let time = chrono::Utc::now().timestamp();
+    let max_log = state.config.max_log;
+
+    let sender_user = state.users.get_mut(sender).unwrap();
+    sender_user.balance -= amount;
+    sender_user.push_log(
+        MockLogEntry {
+            counterparty: recipient.into(),
+            receiving: false,
+            amount,
+            time,
+        },
+        max_log,
+    );
+    let balance = sender_user.balance;
+
+    let recipient_user = state.users.get_mut(recipient).unwrap();
+    recipient_user.balance = recipient_user.balance.saturating_add(amount);
+    recipient_user.push_log(
+        MockLogEntry {
+            counterparty: sender.into(),
+            receiving: true,
+            amount,
+            time,
+        },
+        max_log,
+    );
+
+    respond(StatusCode::OK, &balance)
+}
+
+fn register(
+    state: &mut MockState,
+    name: &str,
+    password: &str,
+    amount: u32,
+) -> Response<Body> {
+    if !is_valid_name(name) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid Name, breaks size and/or character restrictions",
+        );
+    }
+    if state.users.contains_key(name) {
+        return error(StatusCode::CONFLICT, "User already exists");
+    }
+
+    state
+        .users
+        .insert(name.into(), MockUser::new(password, amount));
+    empty(StatusCode::NO_CONTENT)
+}
+
+fn prune(state: &mut MockState, body: &PruneBody) -> u64 {
+    let protected = [
+        Some(state.config.admin_name.clone()),
+        state.config.return_on_del.clone(),
+    ];
+    let pruned = state
+        .users
+        .iter()
+        .filter(|(name, _)| !protected.contains(&Some((*name).clone())))
+        .filter(|(_, user)| user.balance < body.amount)
+        .filter(|(_, user)| match body.time {
+            Some(time) => user.log.last().is_none_or(|e| e.time < time),
+            None => true,
+        })
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+
+    for name in &pruned {
+        delete(state, name);
+    }
+
+    pruned.len() as u64
+}
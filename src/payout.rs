@@ -0,0 +1,383 @@
+//! This module contains functions to pay out event rewards, or make
+//! deductions, to many users at once with [`pay_out`], optionally rolling back
+//! the whole batch if any entry fails.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use ccash_rs::{payout::*, *};
+//!
+//! let mut session = CCashSession::new("https://bank.example.com");
+//! session.establish_connection().await?;
+//! let admin = CCashUser::new("admin", "password")?;
+//!
+//! let entries = vec![
+//!     PayoutEntry::new("alice", 100, "Won the build contest"),
+//!     PayoutEntry::new("bob", -20, "Griefing"),
+//! ];
+//! let options = PayoutOptions::new().rollback_on_failure(true);
+//! let report = pay_out(&session, &admin, entries, &options).await;
+//! println!("{} CSH paid out", report.total_applied());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    methods, CCashApiError, CCashError, CCashSession, CCashUser, Username, UsernamePolicy,
+};
+use futures::{stream, StreamExt};
+
+/// Returns whether the `user` exists, treating a 404 from
+/// [`methods::contains_user`] as the user not existing.
+pub(crate) async fn user_exists(
+    session: &CCashSession,
+    user: &CCashUser,
+) -> crate::Result<bool> {
+    match methods::contains_user(session, user).await {
+        Err(CCashError::ApiError(CCashApiError::UserNotFound { .. })) => Ok(false),
+        result => result,
+    }
+}
+
+/// A single payout or deduction made by [`pay_out`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayoutEntry {
+    pub(crate) username: String,
+    pub(crate) amount: i64,
+    pub(crate) reason: String,
+}
+
+impl PayoutEntry {
+    /// Constructs a new `PayoutEntry` that impacts the balance of the user
+    /// named `username` by `amount`, which is negative for a deduction.
+    #[must_use]
+    pub fn new(username: &str, amount: i64, reason: &str) -> PayoutEntry {
+        Self {
+            username: username.into(),
+            amount,
+            reason: reason.into(),
+        }
+    }
+
+    /// Returns the name of the user.
+    #[must_use]
+    pub fn get_username(&self) -> &str { &self.username }
+
+    /// Returns the amount the balance is impacted by.
+    #[must_use]
+    pub fn get_amount(&self) -> i64 { self.amount }
+
+    /// Returns the reason for the payout.
+    #[must_use]
+    pub fn get_reason(&self) -> &str { &self.reason }
+}
+
+/// Builder that describes how [`pay_out`] runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayoutOptions {
+    concurrency: usize,
+    dry_run: bool,
+    rollback_on_failure: bool,
+    username_policy: UsernamePolicy,
+}
+
+impl Default for PayoutOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            dry_run: false,
+            rollback_on_failure: false,
+            username_policy: UsernamePolicy::default(),
+        }
+    }
+}
+
+impl PayoutOptions {
+    /// Constructs new `PayoutOptions` that make at most 8 requests at a time
+    /// and do not roll back.
+    #[must_use]
+    pub fn new() -> PayoutOptions { Self::default() }
+
+    /// Sets the max amount of requests made at a time.
+    #[must_use]
+    pub fn concurrency(mut self, concurrency: usize) -> PayoutOptions {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets whether this is a dry run. A dry run validates every entry and
+    /// checks that every user exists, without impacting any balances.
+    #[must_use]
+    pub fn dry_run(mut self, dry_run: bool) -> PayoutOptions {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sets whether the batch is all-or-nothing. If any entry is invalid or its
+    /// user does not exist, no balances are impacted, and if any impact fails,
+    /// the impacts that were already applied are reversed.
+    #[must_use]
+    pub fn rollback_on_failure(mut self, rollback: bool) -> PayoutOptions {
+        self.rollback_on_failure = rollback;
+        self
+    }
+
+    /// Sets the [`UsernamePolicy`] that the username of every entry is checked
+    /// against.
+    #[must_use]
+    pub fn username_policy(mut self, policy: UsernamePolicy) -> PayoutOptions {
+        self.username_policy = policy;
+        self
+    }
+}
+
+/// Enum for the status of an entry processed by [`pay_out`], without any
+/// details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayoutStatus {
+    /// See [`PayoutOutcome::Applied`].
+    Applied,
+    /// See [`PayoutOutcome::WouldApply`].
+    WouldApply,
+    /// See [`PayoutOutcome::Skipped`].
+    Skipped,
+    /// See [`PayoutOutcome::UserNotFound`].
+    UserNotFound,
+    /// See [`PayoutOutcome::Invalid`].
+    Invalid,
+    /// See [`PayoutOutcome::Failed`].
+    Failed,
+    /// See [`PayoutOutcome::RolledBack`].
+    RolledBack,
+    /// See [`PayoutOutcome::RollbackFailed`].
+    RollbackFailed,
+}
+
+/// Enum for the outcome of an entry processed by [`pay_out`].
+#[derive(Debug)]
+pub enum PayoutOutcome {
+    /// The balance was impacted.
+    Applied,
+    /// The balance would have been impacted, if this was not a dry run.
+    WouldApply,
+    /// The balance was not impacted, as another entry failed and
+    /// [`rollback_on_failure`](PayoutOptions::rollback_on_failure) is set.
+    Skipped,
+    /// The user does not exist.
+    UserNotFound,
+    /// The entry could not be used, such as when the username is invalid or
+    /// the amount is 0. No request was made for it.
+    Invalid(String),
+    /// The request to check the user or to impact the balance failed. If the
+    /// request timed out, the impact may still have been applied.
+    Failed(CCashError),
+    /// The balance was impacted, and then reversed because another entry
+    /// failed.
+    RolledBack,
+    /// The balance was impacted, but reversing it failed, so it is still
+    /// applied.
+    RollbackFailed(CCashError),
+}
+
+impl PayoutOutcome {
+    /// Returns the [`PayoutStatus`] of this outcome.
+    #[must_use]
+    pub fn status(&self) -> PayoutStatus {
+        match self {
+            PayoutOutcome::Applied => PayoutStatus::Applied,
+            PayoutOutcome::WouldApply => PayoutStatus::WouldApply,
+            PayoutOutcome::Skipped => PayoutStatus::Skipped,
+            PayoutOutcome::UserNotFound => PayoutStatus::UserNotFound,
+            PayoutOutcome::Invalid(_) => PayoutStatus::Invalid,
+            PayoutOutcome::Failed(_) => PayoutStatus::Failed,
+            PayoutOutcome::RolledBack => PayoutStatus::RolledBack,
+            PayoutOutcome::RollbackFailed(_) => PayoutStatus::RollbackFailed,
+        }
+    }
+
+    fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            PayoutOutcome::UserNotFound
+                | PayoutOutcome::Invalid(_)
+                | PayoutOutcome::Failed(_)
+        )
+    }
+}
+
+/// The outcome of a single entry processed by [`pay_out`].
+#[derive(Debug)]
+pub struct PayoutResult {
+    pub(crate) entry: PayoutEntry,
+    pub(crate) outcome: PayoutOutcome,
+}
+
+impl PayoutResult {
+    /// Returns the entry.
+    #[must_use]
+    pub fn get_entry(&self) -> &PayoutEntry { &self.entry }
+
+    /// Returns the outcome of the entry.
+    #[must_use]
+    pub fn get_outcome(&self) -> &PayoutOutcome { &self.outcome }
+}
+
+/// The report returned by [`pay_out`], with a result for every entry, in the
+/// order the entries were given in.
+#[derive(Debug, Default)]
+pub struct PayoutReport {
+    pub(crate) results: Vec<PayoutResult>,
+}
+
+impl PayoutReport {
+    /// Returns the results of every entry, in the order the entries were given
+    /// in.
+    #[must_use]
+    pub fn get_results(&self) -> &[PayoutResult] { &self.results }
+
+    /// Returns the amount of entries with the given `status`.
+    #[must_use]
+    pub fn count(&self, status: PayoutStatus) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.outcome.status() == status)
+            .count()
+    }
+
+    /// Returns the sum of the amounts of the entries that are still applied.
+    #[must_use]
+    pub fn total_applied(&self) -> i64 {
+        self.results
+            .iter()
+            .filter(|r| {
+                matches!(
+                    r.outcome,
+                    PayoutOutcome::Applied | PayoutOutcome::RollbackFailed(_)
+                )
+            })
+            .map(|r| r.entry.amount)
+            .sum()
+    }
+
+    /// Returns whether every entry was applied, or would be applied.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|r| {
+            matches!(
+                r.outcome,
+                PayoutOutcome::Applied | PayoutOutcome::WouldApply
+            )
+        })
+    }
+}
+
+/// Impacts the balance of the user of every entry with
+/// [`admin::impact_balance`](methods::admin::impact_balance), making at most
+/// [`concurrency`](PayoutOptions::concurrency) requests at a time.
+///
+/// Each entry is validated, and every user is checked to exist with
+/// [`contains_user`](methods::contains_user), before any balance is impacted.
+/// If [`rollback_on_failure`](PayoutOptions::rollback_on_failure) is set, no
+/// balances are impacted if any of these checks fail, and impacts that were
+/// applied are reversed if any other impact fails.
+///
+/// Errors are reported per entry in the [`PayoutReport`], so this function
+/// never fails.
+pub async fn pay_out(
+    session: &CCashSession,
+    admin_user: &CCashUser,
+    entries: Vec<PayoutEntry>,
+    options: &PayoutOptions,
+) -> PayoutReport {
+    let checked = stream::iter(entries)
+        .map(|mut entry| async move {
+            let outcome = if entry.amount == 0 {
+                PayoutOutcome::Invalid("Amount is 0".into())
+            } else {
+                match options.username_policy.validate(&entry.username) {
+                    Err(e) => PayoutOutcome::Invalid(e.to_string()),
+                    Ok(username) => {
+                        let user = CCashUser::from_username(username, "");
+                        entry.username = user.username.clone();
+                        match user_exists(session, &user).await {
+                            Ok(true) => PayoutOutcome::WouldApply,
+                            Ok(false) => PayoutOutcome::UserNotFound,
+                            Err(e) => PayoutOutcome::Failed(e),
+                        }
+                    },
+                }
+            };
+
+            PayoutResult { entry, outcome }
+        })
+        .buffered(options.concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    if options.dry_run {
+        return PayoutReport { results: checked };
+    }
+
+    if options.rollback_on_failure && checked.iter().any(|r| r.outcome.is_failure()) {
+        let results = checked
+            .into_iter()
+            .map(|mut r| {
+                if let PayoutOutcome::WouldApply = r.outcome {
+                    r.outcome = PayoutOutcome::Skipped;
+                }
+                r
+            })
+            .collect();
+
+        return PayoutReport { results };
+    }
+
+    let mut results = stream::iter(checked)
+        .map(|mut result| async move {
+            if let PayoutOutcome::WouldApply = result.outcome {
+                let entry = &result.entry;
+                result.outcome = match methods::admin::impact_balance(
+                    session,
+                    admin_user,
+                    &Username::new_unchecked(&entry.username),
+                    entry.amount,
+                )
+                .await
+                {
+                    Ok(()) => PayoutOutcome::Applied,
+                    Err(e) => PayoutOutcome::Failed(e),
+                };
+            }
+
+            result
+        })
+        .buffered(options.concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    if options.rollback_on_failure && results.iter().any(|r| r.outcome.is_failure()) {
+        results = stream::iter(results)
+            .map(|mut result| async move {
+                if let PayoutOutcome::Applied = result.outcome {
+                    let entry = &result.entry;
+                    result.outcome = match methods::admin::impact_balance(
+                        session,
+                        admin_user,
+                        &Username::new_unchecked(&entry.username),
+                        entry.amount.saturating_neg(),
+                    )
+                    .await
+                    {
+                        Ok(()) => PayoutOutcome::RolledBack,
+                        Err(e) => PayoutOutcome::RollbackFailed(e),
+                    };
+                }
+
+                result
+            })
+            .buffered(options.concurrency)
+            .collect()
+            .await;
+    }
+
+    PayoutReport { results }
+}
@@ -0,0 +1,429 @@
+//! This module contains the [`CCashPool`], which holds a [`CCashSession`] for
+//! each of several `CCash` instances, keyed by name.
+//!
+//! Calls are routed to an instance by its name. An instance can be given a
+//! standby instance, which read-only calls fail over to when the instance
+//! cannot be reached. Calls that change the state of an instance are never
+//! failed over, as the standby may not be in sync with it.
+//!
+//! Once an instance could not be reached, its standby is called first until
+//! the [`failback_delay`](CCashPool::with_failback_delay) has passed, after
+//! which the instance is called first again.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use ccash_rs::{pool::*, *};
+//!
+//! let mut pool = CCashPool::new()
+//!     .with_instance(
+//!         "survival",
+//!         CCashSession::new("https://survival.example.com"),
+//!     )
+//!     .with_instance("standby", CCashSession::new("https://standby.example.com"));
+//! pool.set_standby("survival", "standby")?;
+//! pool.connect_all().await;
+//!
+//! let alice = CCashUser::new("alice", "password")?;
+//! let balance = pool.get_balance("survival", &alice).await?;
+//! methods::send_funds(pool.session("survival")?, &alice, "bob", 10).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{methods, CCashError, CCashSession, CCashUser, Csh, TransactionLogV2};
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// Enum for the errors that could occur when using a [`CCashPool`].
+#[derive(Error, Debug)]
+pub enum PoolError {
+    /// An error when no instance with the given name is in the pool.
+    #[error("No instance named \"{0}\" is in the pool")]
+    UnknownInstance(String),
+    /// An error returned by the `CCash` instance, or when it could not be
+    /// reached.
+    #[error("{0}")]
+    CCash(#[from] CCashError),
+}
+
+/// Enum for the health of an instance in a [`CCashPool`], as of the last
+/// health check or call made to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceHealth {
+    /// The instance has not been checked yet.
+    Unknown,
+    /// The instance responded to the last health check or call.
+    Healthy {
+        /// The time it took for the instance to respond.
+        latency: Duration,
+        /// The time the instance was checked or called.
+        checked_at: DateTime<Utc>,
+    },
+    /// The instance could not be reached at the last health check or call.
+    Unhealthy {
+        /// The reason the instance could not be reached.
+        reason: String,
+        /// The time the instance was checked.
+        checked_at: DateTime<Utc>,
+    },
+}
+
+impl InstanceHealth {
+    /// Returns whether the instance could not be reached when it was last
+    /// checked.
+    #[must_use]
+    pub fn is_unhealthy(&self) -> bool {
+        matches!(self, InstanceHealth::Unhealthy { .. })
+    }
+
+    /// Returns whether the instance could not be reached when it was last
+    /// checked, less than `delay` ago.
+    fn is_unhealthy_within(&self, delay: Duration) -> bool {
+        match self {
+            InstanceHealth::Unhealthy { checked_at, .. } =>
+                chrono::Duration::from_std(delay)
+                    .map_or(true, |delay| Utc::now() - *checked_at < delay),
+            _ => false,
+        }
+    }
+
+    fn healthy(latency: Duration) -> InstanceHealth {
+        InstanceHealth::Healthy {
+            latency,
+            checked_at: Utc::now(),
+        }
+    }
+
+    fn unhealthy(error: &CCashError) -> InstanceHealth {
+        InstanceHealth::Unhealthy {
+            reason: error.to_string(),
+            checked_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PoolMember {
+    session: CCashSession,
+    standby: Option<String>,
+    health: Mutex<InstanceHealth>,
+}
+
+impl PoolMember {
+    fn health(&self) -> InstanceHealth {
+        self.health
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn set_health(&self, health: InstanceHealth) {
+        *self.health.lock().unwrap_or_else(PoisonError::into_inner) = health;
+    }
+}
+
+/// The default time after which an instance that could not be reached is
+/// called before its standby again.
+const DEFAULT_FAILBACK_DELAY: Duration = Duration::from_secs(30);
+
+/// A pool of [`CCashSession`]s for several `CCash` instances, keyed by name.
+#[derive(Debug)]
+pub struct CCashPool {
+    members: BTreeMap<String, PoolMember>,
+    failback_delay: Duration,
+}
+
+impl Default for CCashPool {
+    fn default() -> Self {
+        Self {
+            members: BTreeMap::new(),
+            failback_delay: DEFAULT_FAILBACK_DELAY,
+        }
+    }
+}
+
+impl CCashPool {
+    /// Constructs a new, empty `CCashPool`.
+    #[must_use]
+    pub fn new() -> CCashPool { CCashPool::default() }
+
+    /// Sets how long the standby of an instance that could not be reached is
+    /// called before the instance itself, after which the instance is called
+    /// first again. Defaults to 30 seconds.
+    #[must_use]
+    pub fn with_failback_delay(mut self, delay: Duration) -> CCashPool {
+        self.failback_delay = delay;
+        self
+    }
+
+    /// Adds the `session` to this `CCashPool` under `name`, replacing any
+    /// instance with the same name.
+    #[must_use]
+    pub fn with_instance(mut self, name: &str, session: CCashSession) -> CCashPool {
+        self.insert(name, session);
+        self
+    }
+
+    /// Adds the `session` to this `CCashPool` under `name`, returning the
+    /// session it replaced, if any.
+    pub fn insert(&mut self, name: &str, session: CCashSession) -> Option<CCashSession> {
+        let standby = self.members.get(name).and_then(|m| m.standby.clone());
+        self.members
+            .insert(
+                name.into(),
+                PoolMember {
+                    session,
+                    standby,
+                    health: Mutex::new(InstanceHealth::Unknown),
+                },
+            )
+            .map(|m| m.session)
+    }
+
+    /// Removes the instance named `name` from this `CCashPool`, along with any
+    /// standby mappings to it.
+    pub fn remove(&mut self, name: &str) -> Option<CCashSession> {
+        let member = self.members.remove(name)?;
+        for other in self.members.values_mut() {
+            if other.standby.as_deref() == Some(name) {
+                other.standby = None;
+            }
+        }
+        Some(member.session)
+    }
+
+    /// Sets the instance named `standby` as the standby of the instance named
+    /// `primary`, so that read-only calls to `primary` fail over to it.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`PoolError::UnknownInstance`] if either instance is not in
+    /// the pool.
+    pub fn set_standby(&mut self, primary: &str, standby: &str) -> Result<(), PoolError> {
+        if !self.members.contains_key(standby) {
+            return Err(PoolError::UnknownInstance(standby.into()));
+        }
+        self.member_mut(primary)?.standby = Some(standby.into());
+        Ok(())
+    }
+
+    /// Returns the names of the instances in this `CCashPool`.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.members.keys().map(String::as_str)
+    }
+
+    /// Returns the name of the standby of the instance named `name`, if any.
+    #[must_use]
+    pub fn get_standby(&self, name: &str) -> Option<&str> {
+        self.members.get(name)?.standby.as_deref()
+    }
+
+    /// Returns the session of the instance named `name`, if any.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&CCashSession> {
+        self.members.get(name).map(|m| &m.session)
+    }
+
+    /// Returns the session of the instance named `name` mutably, if any.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut CCashSession> {
+        self.members.get_mut(name).map(|m| &mut m.session)
+    }
+
+    /// Returns the session of the instance named `name`, for making calls
+    /// that should not fail over, such as those that change its state.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`PoolError::UnknownInstance`] if the instance is not in
+    /// the pool.
+    pub fn session(&self, name: &str) -> Result<&CCashSession, PoolError> {
+        self.member(name).map(|m| &m.session)
+    }
+
+    /// Returns the health of the instance named `name` as of the last health
+    /// check or call made to it, if it is in the pool.
+    #[must_use]
+    pub fn get_health(&self, name: &str) -> Option<InstanceHealth> {
+        self.members.get(name).map(PoolMember::health)
+    }
+
+    /// Establishes the connection of every instance in this `CCashPool` that
+    /// is not yet connected, returning the result for each instance by name.
+    pub async fn connect_all(&mut self) -> BTreeMap<String, crate::Result<()>> {
+        join_all(self.members.iter_mut().map(|(name, member)| async move {
+            let start = Instant::now();
+            let result = member.session.establish_connection().await;
+            member.set_health(match &result {
+                Ok(()) => InstanceHealth::healthy(start.elapsed()),
+                Err(e) => InstanceHealth::unhealthy(e),
+            });
+            (name.clone(), result)
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Checks the health of the instance named `name` by requesting its
+    /// properties.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`PoolError::UnknownInstance`] if the instance is not in
+    /// the pool.
+    pub async fn check_health(&self, name: &str) -> Result<InstanceHealth, PoolError> {
+        Ok(Self::check_member(self.member(name)?).await)
+    }
+
+    /// Checks the health of every instance in this `CCashPool`, returning the
+    /// health of each instance by name.
+    pub async fn check_all(&self) -> BTreeMap<String, InstanceHealth> {
+        join_all(self.members.iter().map(|(name, member)| async move {
+            (name.clone(), Self::check_member(member).await)
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Gets the balance of the `user` from the instance named `name`, failing
+    /// over to its standby if it cannot be reached.
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`PoolError`] if the instance is not in the pool, or if
+    /// the call fails on the instance and its standby.
+    pub async fn get_balance(
+        &self,
+        name: &str,
+        user: &CCashUser,
+    ) -> Result<Csh, PoolError> {
+        self.read(name, |session| methods::get_balance(session, user))
+            .await
+    }
+
+    /// Gets the transaction logs of the `user` from the instance named `name`,
+    /// failing over to its standby if it cannot be reached.
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`PoolError`] if the instance is not in the pool, or if
+    /// the call fails on the instance and its standby.
+    pub async fn get_log_v2(
+        &self,
+        name: &str,
+        user: &CCashUser,
+    ) -> Result<Vec<TransactionLogV2>, PoolError> {
+        self.read(name, |session| methods::get_log_v2(session, user))
+            .await
+    }
+
+    /// Checks whether the `user` exists on the instance named `name`, failing
+    /// over to its standby if it cannot be reached.
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`PoolError`] if the instance is not in the pool, or if
+    /// the call fails on the instance and its standby.
+    pub async fn contains_user(
+        &self,
+        name: &str,
+        user: &CCashUser,
+    ) -> Result<bool, PoolError> {
+        self.read(name, |session| methods::contains_user(session, user))
+            .await
+    }
+
+    /// Verifies the password of the `user` on the instance named `name`,
+    /// failing over to its standby if it cannot be reached.
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`PoolError`] if the instance is not in the pool, or if
+    /// the call fails on the instance and its standby.
+    pub async fn verify_password(
+        &self,
+        name: &str,
+        user: &CCashUser,
+    ) -> Result<bool, PoolError> {
+        self.read(name, |session| methods::verify_password(session, user))
+            .await
+    }
+
+    fn member(&self, name: &str) -> Result<&PoolMember, PoolError> {
+        self.members
+            .get(name)
+            .ok_or_else(|| PoolError::UnknownInstance(name.into()))
+    }
+
+    fn member_mut(&mut self, name: &str) -> Result<&mut PoolMember, PoolError> {
+        self.members
+            .get_mut(name)
+            .ok_or_else(|| PoolError::UnknownInstance(name.into()))
+    }
+
+    async fn check_member(member: &PoolMember) -> InstanceHealth {
+        let start = Instant::now();
+        let result = match member.session.client_or_build() {
+            Ok(client) => member.session.fetch_properties(&client).await,
+            Err(e) => Err(e),
+        };
+
+        let health = match result {
+            Ok(_) => InstanceHealth::healthy(start.elapsed()),
+            Err(e) => InstanceHealth::unhealthy(&e),
+        };
+        member.set_health(health.clone());
+        health
+    }
+
+    /// Runs the read-only `call` on the instance named `name`, or on its
+    /// standby if the instance cannot be reached. An instance that was
+    /// unhealthy when last checked, within the failback delay, is tried after
+    /// its standby. Every instance that responds is marked as healthy.
+    async fn read<'a, T, F, Fut>(&'a self, name: &str, call: F) -> Result<T, PoolError>
+    where
+        F: Fn(&'a CCashSession) -> Fut,
+        Fut: Future<Output = crate::Result<T>>,
+    {
+        let primary = self.member(name)?;
+        let mut order = vec![primary];
+        if let Some(standby) =
+            primary.standby.as_deref().and_then(|s| self.members.get(s))
+        {
+            if primary.health().is_unhealthy_within(self.failback_delay)
+                && !standby.health().is_unhealthy()
+            {
+                order.insert(0, standby);
+            } else {
+                order.push(standby);
+            }
+        }
+
+        let mut last_error = None;
+        for member in order {
+            let start = Instant::now();
+            match call(&member.session).await {
+                Err(e) if e.is_connection_error() => {
+                    member.set_health(InstanceHealth::unhealthy(&e));
+                    last_error = Some(e);
+                },
+                result => {
+                    member.set_health(InstanceHealth::healthy(start.elapsed()));
+                    return Ok(result?);
+                },
+            }
+        }
+
+        Err(last_error
+            .unwrap_or(CCashError::ConnectionNotAvailable)
+            .into())
+    }
+}
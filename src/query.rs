@@ -0,0 +1,170 @@
+//! This module contains the [`LogQuery`] builder, used to filter, sort and
+//! limit the [`TransactionLogV2`] entries returned by
+//! [`get_log_v2`](crate::methods::get_log_v2).
+//!
+//! ```
+//! use ccash_rs::query::{LogQuery, SortOrder, TransactionDirection};
+//! use chrono::{Duration, Utc};
+//!
+//! let query = LogQuery::new()
+//!     .since(Utc::now() - Duration::days(1))
+//!     .direction(TransactionDirection::Received)
+//!     .min_amount(100)
+//!     .order(SortOrder::OldestFirst)
+//!     .limit(10);
+//! ```
+
+use crate::{Csh, TransactionLogV2};
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+
+/// Enum for the direction of a transaction, from the point of view of the user
+/// whose logs are being queried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransactionDirection {
+    /// The user sent funds to the counterparty.
+    Sent,
+    /// The user received funds from the counterparty.
+    Received,
+}
+
+/// Enum for the order that logs are sorted in by time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    /// The most recent transactions come first.
+    NewestFirst,
+    /// The oldest transactions come first.
+    OldestFirst,
+}
+
+/// Builder that describes which [`TransactionLogV2`] entries to keep and in
+/// what order. An empty `LogQuery` keeps every entry in the order returned by
+/// `CCash`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogQuery {
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    counterparty: Option<String>,
+    direction: Option<TransactionDirection>,
+    min_amount: Option<Csh>,
+    max_amount: Option<Csh>,
+    order: Option<SortOrder>,
+    limit: Option<usize>,
+}
+
+impl LogQuery {
+    /// Constructs a new `LogQuery` that keeps every entry.
+    #[must_use]
+    pub fn new() -> LogQuery { Self::default() }
+
+    /// Only keeps transactions made at or after `since`.
+    #[must_use]
+    pub fn since(mut self, since: DateTime<Utc>) -> LogQuery {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only keeps transactions made before `until`.
+    #[must_use]
+    pub fn until(mut self, until: DateTime<Utc>) -> LogQuery {
+        self.until = Some(until);
+        self
+    }
+
+    /// Only keeps transactions made at or after `since` and before `until`.
+    #[must_use]
+    pub fn between(self, since: DateTime<Utc>, until: DateTime<Utc>) -> LogQuery {
+        self.since(since).until(until)
+    }
+
+    /// Only keeps transactions with the user named `counterparty`.
+    #[must_use]
+    pub fn counterparty(mut self, counterparty: &str) -> LogQuery {
+        self.counterparty = Some(counterparty.to_lowercase());
+        self
+    }
+
+    /// Only keeps transactions in the given `direction`.
+    #[must_use]
+    pub fn direction(mut self, direction: TransactionDirection) -> LogQuery {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Only keeps transactions of at least `amount`.
+    #[must_use]
+    pub fn min_amount(mut self, amount: impl Into<Csh>) -> LogQuery {
+        self.min_amount = Some(amount.into());
+        self
+    }
+
+    /// Only keeps transactions of at most `amount`.
+    #[must_use]
+    pub fn max_amount(mut self, amount: impl Into<Csh>) -> LogQuery {
+        self.max_amount = Some(amount.into());
+        self
+    }
+
+    /// Sorts the kept transactions by time in the given `order`. Transactions
+    /// made in the same second stay in the order they were made in.
+    #[must_use]
+    pub fn order(mut self, order: SortOrder) -> LogQuery {
+        self.order = Some(order);
+        self
+    }
+
+    /// Only keeps the first `limit` transactions, after sorting.
+    #[must_use]
+    pub fn limit(mut self, limit: usize) -> LogQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns whether the `log` passes the filters of this `LogQuery`. The
+    /// order and limit are not taken into account.
+    #[must_use]
+    pub fn matches(&self, log: &TransactionLogV2) -> bool {
+        let direction = if log.receiving {
+            TransactionDirection::Received
+        } else {
+            TransactionDirection::Sent
+        };
+
+        self.since.is_none_or(|since| log.time >= since.timestamp())
+            && self.until.is_none_or(|until| log.time < until.timestamp())
+            && self
+                .counterparty
+                .as_ref()
+                .is_none_or(|c| *c == log.counterparty.to_lowercase())
+            && self.direction.is_none_or(|d| d == direction)
+            && self.min_amount.is_none_or(|min| log.amount >= min)
+            && self.max_amount.is_none_or(|max| log.amount <= max)
+    }
+
+    /// Applies this `LogQuery` to the `logs`, returning the entries that pass
+    /// the filters, sorted and limited.
+    #[must_use]
+    pub fn apply(&self, logs: Vec<TransactionLogV2>) -> Vec<TransactionLogV2> {
+        let mut logs = logs
+            .into_iter()
+            .filter(|log| self.matches(log))
+            .collect::<Vec<_>>();
+
+        match self.order {
+            Some(SortOrder::NewestFirst) => logs.sort_by_key(|log| Reverse(log.time)),
+            Some(SortOrder::OldestFirst) => {
+                // `CCash` returns the newest transactions first, so reversing
+                // keeps transactions made in the same second in order.
+                logs.reverse();
+                logs.sort_by_key(|log| log.time);
+            },
+            None => {},
+        }
+
+        if let Some(limit) = self.limit {
+            logs.truncate(limit);
+        }
+
+        logs
+    }
+}
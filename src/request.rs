@@ -1,37 +1,412 @@
-use crate::{user, CCashError, CCashResponse, CCashSession};
+use crate::{
+    connection::ConnectionState, user, CCashError, CCashResponse, CCashSession,
+    CCashSessionProperties, Capability,
+};
 use reqwest::{Client, Method};
 use serde::Serialize;
+use std::time::{Duration, Instant};
 
-fn get_client(session: &CCashSession) -> Result<Client, CCashError> {
-    if !session.is_connected() {
-        return Err(CCashError::ConnectionNotAvailable);
+/// Every endpoint of the `CCash` API that `ccash-rs` calls. Used to build the
+/// request and to interpret the error responses of each endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Endpoint {
+    GetBalance,
+    GetLog,
+    GetLogV2,
+    ContainsUser,
+    VerifyPassword,
+    ChangePassword,
+    SendFunds,
+    AddUser,
+    DeleteUser,
+    AdminVerifyAccount,
+    AdminChangePassword,
+    AdminSetBalance,
+    AdminImpactBalance,
+    AdminAddUser,
+    AdminDeleteUser,
+    AdminPruneUsers,
+    AdminClose,
+}
+
+impl Endpoint {
+    pub(crate) fn method(self) -> Method {
+        match self {
+            Self::GetBalance | Self::GetLog | Self::GetLogV2 | Self::ContainsUser =>
+                Method::GET,
+            Self::VerifyPassword
+            | Self::SendFunds
+            | Self::AddUser
+            | Self::AdminVerifyAccount
+            | Self::AdminImpactBalance
+            | Self::AdminAddUser
+            | Self::AdminPruneUsers
+            | Self::AdminClose => Method::POST,
+            Self::ChangePassword | Self::AdminChangePassword | Self::AdminSetBalance =>
+                Method::PATCH,
+            Self::DeleteUser | Self::AdminDeleteUser => Method::DELETE,
+        }
+    }
+
+    pub(crate) fn path(self) -> &'static str {
+        match self {
+            Self::GetBalance => "/v1/user/balance",
+            Self::GetLog => "/v1/user/log",
+            Self::GetLogV2 => "/v2/user/log",
+            Self::ContainsUser => "/v1/user/exists",
+            Self::VerifyPassword => "/v1/user/verify_password",
+            Self::ChangePassword => "/v1/user/change_password",
+            Self::SendFunds => "/v1/user/transfer",
+            Self::AddUser => "/v1/user/register",
+            Self::DeleteUser => "/v1/user/delete",
+            Self::AdminVerifyAccount => "/v1/admin/verify_account",
+            Self::AdminChangePassword => "/v1/admin/user/change_password",
+            Self::AdminSetBalance => "/v1/admin/set_balance",
+            Self::AdminImpactBalance => "/v1/admin/impact_balance",
+            Self::AdminAddUser => "/v1/admin/user/register",
+            Self::AdminDeleteUser => "/v1/admin/user/delete",
+            Self::AdminPruneUsers => "/v1/admin/prune_users",
+            Self::AdminClose => "/v1/admin/shutdown",
+        }
+    }
+
+    /// Whether calling the endpoint more than once has the same effect as
+    /// calling it once, so that it can be safely retried.
+    pub(crate) fn is_idempotent(self) -> bool {
+        matches!(
+            self,
+            Self::GetBalance
+                | Self::GetLog
+                | Self::GetLogV2
+                | Self::ContainsUser
+                | Self::VerifyPassword
+                | Self::AdminVerifyAccount
+        )
     }
-    Ok(session.get_client().clone().unwrap())
+
+    /// The capability that the `CCash` instance must support for the endpoint
+    /// to be available, if any.
+    pub(crate) fn capability(self) -> Option<Capability> {
+        match self {
+            Self::GetLogV2 => Some(Capability::LogV2),
+            Self::AddUser => Some(Capability::OpenRegistration),
+            _ => None,
+        }
+    }
+
+    /// Whether the endpoint requires the admin account of the `CCash`
+    /// instance.
+    pub(crate) fn is_admin(self) -> bool {
+        matches!(
+            self,
+            Self::AdminVerifyAccount
+                | Self::AdminChangePassword
+                | Self::AdminSetBalance
+                | Self::AdminImpactBalance
+                | Self::AdminAddUser
+                | Self::AdminDeleteUser
+                | Self::AdminPruneUsers
+                | Self::AdminClose
+        )
+    }
+}
+
+/// Returns an error if the `endpoint` is not available on the `CCash` instance
+/// with the given `properties`.
+fn check_supported(
+    properties: Option<&CCashSessionProperties>,
+    endpoint: Endpoint,
+) -> Result<(), CCashError> {
+    match (properties, endpoint.capability()) {
+        (Some(properties), Some(capability)) if !properties.supports(capability) =>
+            Err(CCashError::UnsupportedByServer(capability)),
+        _ => Ok(()),
+    }
+}
+
+/// Records the outcome of an `attempt` at a request in the current span, and
+/// emits an event at a level that matches the outcome: rejected requests are
+/// expected, so only server errors and requests that could not be sent are
+/// reported as errors.
+#[cfg(feature = "tracing")]
+fn trace_attempt(
+    attempt: u32,
+    latency: Duration,
+    outcome: Result<&CCashResponse, &reqwest::Error>,
+    retry: Option<Duration>,
+) {
+    let span = tracing::Span::current();
+    let latency_ms = latency.as_secs_f64() * 1000.0;
+    span.record("attempts", attempt);
+    span.record("latency_ms", latency_ms);
+
+    let status = match outcome {
+        Ok(CCashResponse::Success { code, .. } | CCashResponse::Error { code, .. }) => {
+            span.record("status", code);
+            Some(*code)
+        },
+        Err(_) => None,
+    };
+    let error = outcome.err().map(tracing::field::display);
+
+    if let Some(delay) = retry {
+        let retry_in_ms = u64::try_from(delay.as_millis()).unwrap_or(u64::MAX);
+        tracing::warn!(
+            attempt,
+            status,
+            latency_ms,
+            retry_in_ms,
+            error,
+            "retrying request"
+        );
+        return;
+    }
+
+    match status {
+        Some(200..=299) =>
+            tracing::debug!(attempt, status, latency_ms, "request succeeded"),
+        Some(400..=499) =>
+            tracing::info!(attempt, status, latency_ms, "request rejected"),
+        _ => tracing::error!(attempt, status, latency_ms, error, "request failed"),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+fn trace_attempt(
+    _attempt: u32,
+    _latency: Duration,
+    _outcome: Result<&CCashResponse, &reqwest::Error>,
+    _retry: Option<Duration>,
+) {
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "ccash_request",
+        level = "debug",
+        skip_all,
+        fields(
+            endpoint = endpoint.path(),
+            method = %endpoint.method(),
+            user = user.map(|user| user.username.as_str()),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+            attempts = tracing::field::Empty,
+        ),
+    )
+)]
 pub async fn request<Body: Serialize>(
-    method: Method,
     session: &CCashSession,
-    uri: &str,
+    endpoint: Endpoint,
     user: Option<&user::CCashUser>,
     body: Option<&Body>,
+    query: &[(&str, &str)],
 ) -> Result<CCashResponse, CCashError> {
-    let client = get_client(session)?;
+    let policy = session.get_retry_policy();
+    let state = session.connection.read().clone();
+    let mut has_reconnected = false;
+    let (mut client, properties) = match state {
+        ConnectionState {
+            is_connected: true,
+            client: Some(client),
+            properties,
+        } => (client, properties),
+        _ if policy.get_reconnect() => {
+            has_reconnected = true;
+            let (client, properties) = reestablish(session)
+                .await
+                .ok_or(CCashError::ConnectionNotAvailable)?;
+            (client, Some(properties))
+        },
+        _ => return Err(CCashError::ConnectionNotAvailable),
+    };
+    check_supported(properties.as_ref(), endpoint)?;
+
+    let mut attempt = 1;
+    loop {
+        let mut builder = client
+            .request(
+                endpoint.method(),
+                format!("{}{}", &session.session_url, endpoint.path()),
+            )
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json");
+
+        if !query.is_empty() {
+            builder = builder.query(query);
+        }
+        if let Some(user) = user {
+            builder = builder.basic_auth(&user.username, Some(user.expose_password()));
+        }
+        if let Some(body) = body {
+            builder = builder.json(&body);
+        }
 
-    let mut builder = client
-        .request(method, uri)
-        .header("Accept", "application/json")
-        .header("Content-Type", "application/json");
+        let start = Instant::now();
+        let outcome = match client.execute(builder.build()?).await {
+            Ok(r) => Ok(CCashResponse::from_response(r).await),
+            Err(e) => Err(e),
+        };
 
-    if let Some(user) = user {
-        builder = builder.basic_auth(&user.username, Some(&user.password));
+        let retry = policy.retry_delay(endpoint, attempt, outcome.as_ref());
+        trace_attempt(attempt, start.elapsed(), outcome.as_ref(), retry);
+        match retry {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None if !has_reconnected
+                && policy.should_reconnect(endpoint, outcome.as_ref()) =>
+            {
+                has_reconnected = true;
+                let Some((new_client, properties)) = reestablish(session).await else {
+                    return outcome.map_err(CCashError::from);
+                };
+                check_supported(Some(&properties), endpoint)?;
+                client = new_client;
+            },
+            None => return outcome.map_err(CCashError::from),
+        }
+        attempt += 1;
     }
-    if let Some(body) = body {
-        builder = builder.json(&body);
+}
+
+/// Re-establishes the connection to the `CCash` instance of the `session` with
+/// a new client, returning it along with the refreshed properties of the
+/// instance, or `None` if the instance still cannot be reached. Both are kept
+/// in the `session` for every later request, or the `session` is marked as not
+/// connected if the instance cannot be reached.
+async fn reestablish(session: &CCashSession) -> Option<(Client, CCashSessionProperties)> {
+    #[cfg(feature = "tracing")]
+    tracing::warn!("re-establishing the connection to the CCash instance");
+
+    let connection = match session.client_config.build() {
+        Ok(client) => session
+            .fetch_properties(&client)
+            .await
+            .map(|properties| (client, properties)),
+        Err(e) => Err(e.into()),
+    };
+
+    let mut state = session.connection.write();
+    if let Ok((client, properties)) = connection {
+        state.connect(client.clone(), properties.clone());
+        Some((client, properties))
+    } else {
+        state.is_connected = false;
+        None
     }
+}
 
-    match client.execute(builder.build()?).await {
-        Ok(r) => Ok(CCashResponse::from_response(r).await),
+#[cfg(feature = "blocking")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "ccash_request",
+        level = "debug",
+        skip_all,
+        fields(
+            endpoint = endpoint.path(),
+            method = %endpoint.method(),
+            user = user.map(|user| user.username.as_str()),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+            attempts = tracing::field::Empty,
+        ),
+    )
+)]
+pub fn blocking_request<Body: Serialize>(
+    session: &crate::blocking::BlockingCCashSession,
+    endpoint: Endpoint,
+    user: Option<&user::CCashUser>,
+    body: Option<&Body>,
+    query: &[(&str, &str)],
+) -> Result<CCashResponse, CCashError> {
+    let policy = session.get_retry_policy();
+    let state = session.connection.read().clone();
+    let mut has_reconnected = false;
+    let (mut client, properties) = match state {
+        ConnectionState {
+            is_connected: true,
+            client: Some(client),
+            properties,
+        } => (client, properties),
+        _ if policy.get_reconnect() => {
+            has_reconnected = true;
+            let (client, properties) = blocking_reestablish(session)
+                .ok_or(CCashError::ConnectionNotAvailable)?;
+            (client, Some(properties))
+        },
+        _ => return Err(CCashError::ConnectionNotAvailable),
+    };
+    check_supported(properties.as_ref(), endpoint)?;
+
+    let mut attempt = 1;
+    loop {
+        let mut builder = client
+            .request(
+                endpoint.method(),
+                format!("{}{}", &session.session_url, endpoint.path()),
+            )
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json");
+
+        if !query.is_empty() {
+            builder = builder.query(query);
+        }
+        if let Some(user) = user {
+            builder = builder.basic_auth(&user.username, Some(user.expose_password()));
+        }
+        if let Some(body) = body {
+            builder = builder.json(&body);
+        }
+
+        let start = Instant::now();
+        let outcome = match client.execute(builder.build()?) {
+            Ok(r) => Ok(CCashResponse::from_blocking_response(r)),
+            Err(e) => Err(e),
+        };
+
+        let retry = policy.retry_delay(endpoint, attempt, outcome.as_ref());
+        trace_attempt(attempt, start.elapsed(), outcome.as_ref(), retry);
+        match retry {
+            Some(delay) => std::thread::sleep(delay),
+            None if !has_reconnected
+                && policy.should_reconnect(endpoint, outcome.as_ref()) =>
+            {
+                has_reconnected = true;
+                let Some((new_client, properties)) = blocking_reestablish(session) else {
+                    return outcome.map_err(CCashError::from);
+                };
+                check_supported(Some(&properties), endpoint)?;
+                client = new_client;
+            },
+            None => return outcome.map_err(CCashError::from),
+        }
+        attempt += 1;
+    }
+}
+
+/// Blocking version of [`reestablish`].
+#[cfg(feature = "blocking")]
+fn blocking_reestablish(
+    session: &crate::blocking::BlockingCCashSession,
+) -> Option<(reqwest::blocking::Client, CCashSessionProperties)> {
+    #[cfg(feature = "tracing")]
+    tracing::warn!("re-establishing the connection to the CCash instance");
+
+    let connection = match session.client_config.build() {
+        Ok(client) => session
+            .fetch_properties(&client)
+            .map(|properties| (client, properties)),
         Err(e) => Err(e.into()),
+    };
+
+    let mut state = session.connection.write();
+    if let Ok((client, properties)) = connection {
+        state.connect(client.clone(), properties.clone());
+        Some((client, properties))
+    } else {
+        state.is_connected = false;
+        None
     }
 }
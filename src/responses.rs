@@ -2,8 +2,9 @@
 //! responses when attempting to connect to `CCash` and error responses from
 //! `CCash`.
 
-use crate::CCashUsernameError;
-use reqwest::Response;
+use crate::{request::Endpoint, CCashUsernameError, Capability};
+use reqwest::{Response, StatusCode};
+use serde::de::DeserializeOwned;
 use thiserror::Error;
 
 /// Enum for all the possible responses from the `CCash` endpoints.
@@ -33,15 +34,28 @@ impl std::fmt::Display for CCashResponse {
 
 impl CCashResponse {
     pub(crate) async fn from_response(response: Response) -> CCashResponse {
-        if response.status().is_success() {
+        let status = response.status();
+        Self::from_parts(status, response.text().await.unwrap_or_default())
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn from_blocking_response(
+        response: reqwest::blocking::Response,
+    ) -> CCashResponse {
+        let status = response.status();
+        Self::from_parts(status, response.text().unwrap_or_default())
+    }
+
+    fn from_parts(status: StatusCode, message: String) -> CCashResponse {
+        if status.is_success() {
             Self::Success {
-                code: response.status().as_u16(),
-                message: response.text().await.unwrap_or_default(),
+                code: status.as_u16(),
+                message,
             }
         } else {
             Self::Error {
-                code: response.status().as_u16(),
-                message: response.text().await.unwrap_or_default(),
+                code: status.as_u16(),
+                message,
             }
         }
     }
@@ -57,6 +71,179 @@ impl CCashResponse {
             Err(())
         }
     }
+
+    /// Converts this response into the [`CCashError`] that best describes it
+    /// for the given `endpoint`.
+    pub(crate) fn into_error(self, endpoint: Endpoint) -> CCashError {
+        match self {
+            Self::Success { .. } => CCashError::ErrorResponse(self),
+            Self::Error { code, message } =>
+                CCashApiError::from_endpoint_response(endpoint, code, &message).into(),
+        }
+    }
+
+    /// Parses the message of a successful response into `T`, otherwise returns
+    /// the error for the `endpoint`.
+    pub(crate) fn into_value<T: DeserializeOwned + Default>(
+        self,
+        endpoint: Endpoint,
+    ) -> Result<T> {
+        if let Ok(v) = self.convert_message::<T>() {
+            Ok(v)
+        } else {
+            Err(self.into_error(endpoint))
+        }
+    }
+
+    /// Maps a successful response to `Ok(true)`. Error responses with the
+    /// status code `false_on` are mapped to `Ok(false)`, as are all error
+    /// responses if the `interpret_endpoint_errors_as_false` feature is
+    /// enabled.
+    #[cfg_attr(
+        feature = "interpret_endpoint_errors_as_false",
+        allow(clippy::unnecessary_wraps)
+    )]
+    pub(crate) fn into_bool(
+        self,
+        endpoint: Endpoint,
+        false_on: Option<u16>,
+    ) -> Result<bool> {
+        match self {
+            Self::Success { .. } => Ok(true),
+            #[cfg(feature = "interpret_endpoint_errors_as_false")]
+            Self::Error { .. } => {
+                let _ = (endpoint, false_on);
+                Ok(false)
+            },
+            #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
+            Self::Error { code, .. } if Some(code) == false_on => Ok(false),
+            #[cfg(not(feature = "interpret_endpoint_errors_as_false"))]
+            Self::Error { .. } => Err(self.into_error(endpoint)),
+        }
+    }
+
+    /// Maps a successful response to `Ok(())`, otherwise returns the error for
+    /// the `endpoint`.
+    pub(crate) fn into_unit(self, endpoint: Endpoint) -> Result<()> {
+        match self {
+            Self::Success { .. } => Ok(()),
+            Self::Error { .. } => Err(self.into_error(endpoint)),
+        }
+    }
+}
+
+/// Enum for the errors that a `CCash` instance can respond with. These are
+/// derived from the status code and message of the response for the endpoint
+/// that was called, so that callers don't need to match on bare status codes.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CCashApiError {
+    /// The username or password of the user was not accepted (401).
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+    /// The endpoint requires the admin account of the `CCash` instance, and the
+    /// given credentials were not accepted as such (401).
+    #[error("Admin credentials are required")]
+    AdminRequired,
+    /// The user that was referred to does not exist on the `CCash` instance
+    /// (404).
+    #[error("User not found: {message}")]
+    UserNotFound {
+        /// The message of the `CCash` response.
+        message: String,
+    },
+    /// The sending user does not have enough funds to complete the transfer
+    /// (400).
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+    /// A user with the given name already exists on the `CCash` instance (409).
+    #[error("Name is already taken")]
+    NameTaken,
+    /// The name of the new user was rejected by the `CCash` instance (400).
+    #[error("Invalid name: {message}")]
+    InvalidName {
+        /// The message of the `CCash` response.
+        message: String,
+    },
+    /// The request was rejected by the `CCash` instance for any other reason
+    /// (4xx).
+    #[error("Invalid request ({code}): {message}")]
+    InvalidRequest {
+        /// The status code of the `CCash` response.
+        code: u16,
+        /// The message of the `CCash` response.
+        message: String,
+    },
+    /// The `CCash` instance ran into an internal error (5xx).
+    #[error("Server error ({code}): {message}")]
+    ServerError {
+        /// The status code of the `CCash` response.
+        code: u16,
+        /// The message of the `CCash` response.
+        message: String,
+    },
+    /// The `CCash` instance responded with a status code that isn't expected
+    /// from any endpoint.
+    #[error("Unexpected response ({code}): {message}")]
+    Unexpected {
+        /// The status code of the `CCash` response.
+        code: u16,
+        /// The message of the `CCash` response.
+        message: String,
+    },
+}
+
+impl CCashApiError {
+    /// Returns the status code that `CCash` responded with, if known.
+    #[must_use]
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::InvalidCredentials | Self::AdminRequired => Some(401),
+            Self::UserNotFound { .. } => Some(404),
+            Self::InsufficientFunds | Self::InvalidName { .. } => Some(400),
+            Self::NameTaken => Some(409),
+            Self::InvalidRequest { code, .. }
+            | Self::ServerError { code, .. }
+            | Self::Unexpected { code, .. } => Some(*code),
+        }
+    }
+
+    /// Classifies an error response of a `CCash` endpoint without knowing
+    /// which endpoint responded.
+    #[must_use]
+    pub fn from_response(code: u16, message: &str) -> Self {
+        Self::classify(None, code, message)
+    }
+
+    pub(crate) fn from_endpoint_response(
+        endpoint: Endpoint,
+        code: u16,
+        message: &str,
+    ) -> Self {
+        Self::classify(Some(endpoint), code, message)
+    }
+
+    fn classify(endpoint: Option<Endpoint>, code: u16, message: &str) -> Self {
+        // `CCash` responds with JSON strings, so the message is unquoted where
+        // possible.
+        let message =
+            serde_json::from_str::<String>(message).unwrap_or_else(|_| message.into());
+        let lowercase = message.to_lowercase();
+
+        match (code, endpoint) {
+            (401, Some(e)) if e.is_admin() => Self::AdminRequired,
+            (401, _) => Self::InvalidCredentials,
+            (404, _) => Self::UserNotFound { message },
+            (409, _) => Self::NameTaken,
+            (400, Some(Endpoint::SendFunds | Endpoint::AdminImpactBalance) | None)
+                if lowercase.contains("insufficient") =>
+                Self::InsufficientFunds,
+            (400 | 406, Some(Endpoint::AddUser | Endpoint::AdminAddUser)) =>
+                Self::InvalidName { message },
+            (400..=499, _) => Self::InvalidRequest { code, message },
+            (500..=599, _) => Self::ServerError { code, message },
+            _ => Self::Unexpected { code, message },
+        }
+    }
 }
 
 /// Enum for all errors that could occur when receiving a response from a
@@ -64,7 +251,7 @@ impl CCashResponse {
 #[derive(Error, Debug)]
 pub enum CCashError {
     /// An error that could be generated when interacting with usernames on
-    /// CCash.
+    /// `CCash`.
     #[error("An error occurred with a username: {0}")]
     UsernameError(#[from] CCashUsernameError),
     /// A reqwest error.
@@ -85,6 +272,13 @@ pub enum CCashError {
     )]
     CouldNotParsePropertiesResponse,
     /// An error returned by the `CCash` instance itself.
+    #[error("The `CCash` server rejected the request: {0}")]
+    ApiError(#[from] CCashApiError),
+    /// An error when the endpoint that was called is not available on the
+    /// connected `CCash` instance, as negotiated from its properties.
+    #[error("The `CCash` server does not support {0}")]
+    UnsupportedByServer(Capability),
+    /// A response from the `CCash` instance that could not be interpreted.
     #[error("The `CCash` server responded with {0}")]
     ErrorResponse(CCashResponse),
     /// An returned if `ccash-rs` runs into an internal problem.
@@ -92,8 +286,32 @@ pub enum CCashError {
     Error(String),
 }
 
+impl CCashError {
+    /// Returns whether this error was caused by the `CCash` instance being
+    /// unreachable or unavailable, rather than by the request itself. This
+    /// includes requests whose connection was closed before a response was
+    /// received, such as when the instance has gone down since the connection
+    /// was opened.
+    pub(crate) fn is_connection_error(&self) -> bool {
+        match self {
+            CCashError::ConnectionNotAvailable => true,
+            CCashError::ReqwestError(e) =>
+                e.is_connect() || e.is_timeout() || e.is_request(),
+            CCashError::ApiError(CCashApiError::ServerError { code, .. }) =>
+                matches!(code, 502..=504),
+            _ => false,
+        }
+    }
+}
+
 impl From<CCashResponse> for CCashError {
-    fn from(r: CCashResponse) -> Self { CCashError::ErrorResponse(r) }
+    fn from(r: CCashResponse) -> Self {
+        match r {
+            CCashResponse::Success { .. } => CCashError::ErrorResponse(r),
+            CCashResponse::Error { code, message } =>
+                CCashApiError::from_response(code, &message).into(),
+        }
+    }
 }
 
 /// Convenience `Result` type for `ccash-rs` to deal with the relevant errors
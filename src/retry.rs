@@ -0,0 +1,234 @@
+//! This module contains the [`RetryPolicy`] that describes how requests to a
+//! `CCash` instance are retried when they fail with a transient error.
+
+use crate::{request::Endpoint, CCashResponse};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+/// Struct that describes if and how a failed request to the `CCash` instance
+/// is retried, using exponential backoff between attempts.
+///
+/// Only idempotent calls (such as [`get_balance`](crate::methods::get_balance),
+/// [`contains_user`](crate::methods::contains_user),
+/// [`get_log_v2`](crate::methods::get_log_v2) and
+/// [`verify_password`](crate::methods::verify_password)) are retried by
+/// default. Retrying a call like [`send_funds`](crate::methods::send_funds) or
+/// [`impact_balance`](crate::methods::admin::impact_balance) after a timeout
+/// could apply it twice, so this requires opting in with
+/// [`with_non_idempotent_retries`](RetryPolicy::with_non_idempotent_retries).
+/// Requests that fail to connect never reach the `CCash` instance, so these are
+/// retried regardless of the call.
+///
+/// If a request still cannot reach the instance once its attempts run out, or
+/// the session is not connected when the request is made, the connection is
+/// re-established with a new client and the properties of the instance are
+/// fetched again. The new connection is kept by the session for every later
+/// request, and the request is attempted one last time. This can be turned off
+/// with [`with_reconnect`](RetryPolicy::with_reconnect).
+///
+/// The default `RetryPolicy` never retries a request, but does re-establish the
+/// connection. [`RetryPolicy::none`] does neither.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retry_on_status: Vec<u16>,
+    retry_on_connect_error: bool,
+    retry_on_timeout: bool,
+    retry_non_idempotent: bool,
+    reconnect: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self { Self::none().with_reconnect(true) }
+}
+
+impl RetryPolicy {
+    /// Returns a `RetryPolicy` that never retries a request or re-establishes
+    /// the connection.
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            reconnect: false,
+            ..Self::exponential(1)
+        }
+    }
+
+    /// Returns a `RetryPolicy` that makes at most `max_attempts` attempts
+    /// (including the first), starting with a backoff of 100ms that doubles
+    /// after every attempt up to 10s, with 20% jitter. Connection errors,
+    /// timeouts and 502, 503 and 504 responses are retried, and the connection
+    /// is re-established if they persist.
+    #[must_use]
+    pub fn exponential(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retry_on_status: vec![502, 503, 504],
+            retry_on_connect_error: true,
+            retry_on_timeout: true,
+            retry_non_idempotent: false,
+            reconnect: true,
+        }
+    }
+
+    /// Sets the max amount of attempts made for a request, including the first.
+    #[must_use]
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the backoff before the first retry, and the max backoff between any
+    /// two attempts.
+    #[must_use]
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Sets the factor the backoff is multiplied by after every attempt.
+    #[must_use]
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the fraction (between `0.0` and `1.0`) of the backoff that is
+    /// randomised, so that many clients don't retry at the same time.
+    #[must_use]
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the status codes of `CCash` responses that are retried.
+    #[must_use]
+    pub fn with_retry_on_status(mut self, codes: &[u16]) -> Self {
+        self.retry_on_status = codes.to_vec();
+        self
+    }
+
+    /// Sets whether requests that fail to connect to the `CCash` instance are
+    /// retried.
+    #[must_use]
+    pub fn with_retry_on_connect_error(mut self, retry: bool) -> Self {
+        self.retry_on_connect_error = retry;
+        self
+    }
+
+    /// Sets whether requests that time out are retried.
+    #[must_use]
+    pub fn with_retry_on_timeout(mut self, retry: bool) -> Self {
+        self.retry_on_timeout = retry;
+        self
+    }
+
+    /// Sets whether non-idempotent calls, such as
+    /// [`send_funds`](crate::methods::send_funds) and
+    /// [`impact_balance`](crate::methods::admin::impact_balance), are retried
+    /// after timeouts and retryable responses. This could cause the call to be
+    /// applied more than once by the `CCash` instance.
+    #[must_use]
+    pub fn with_non_idempotent_retries(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    /// Sets whether the connection is re-established and the request attempted
+    /// once more when it still cannot reach the `CCash` instance after its last
+    /// attempt.
+    #[must_use]
+    pub fn with_reconnect(mut self, reconnect: bool) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Returns the max amount of attempts made for a request, including the
+    /// first.
+    #[must_use]
+    pub fn get_max_attempts(&self) -> u32 { self.max_attempts }
+
+    /// Returns whether the connection is re-established when a request cannot
+    /// reach the `CCash` instance, or when the session is not connected.
+    #[must_use]
+    pub fn get_reconnect(&self) -> bool { self.reconnect }
+
+    /// Returns the backoff before the retry following the given `attempt`
+    /// (starting at 1), without jitter.
+    #[must_use]
+    pub fn get_backoff(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+
+        Duration::from_secs_f64(backoff.min(self.max_backoff.as_secs_f64()))
+    }
+
+    /// Returns how long to wait before retrying the request to `endpoint`
+    /// after the given `attempt` (starting at 1) resulted in `outcome`, or
+    /// `None` if the request should not be retried.
+    pub(crate) fn retry_delay(
+        &self,
+        endpoint: Endpoint,
+        attempt: u32,
+        outcome: std::result::Result<&CCashResponse, &reqwest::Error>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        let may_repeat = endpoint.is_idempotent() || self.retry_non_idempotent;
+        let retryable = match outcome {
+            Ok(CCashResponse::Error { code, .. }) =>
+                may_repeat && self.retry_on_status.contains(code),
+            Err(e) if e.is_connect() => self.retry_on_connect_error,
+            Err(e) if e.is_timeout() => may_repeat && self.retry_on_timeout,
+            Ok(CCashResponse::Success { .. }) | Err(_) => false,
+        };
+
+        retryable.then(|| self.jittered(self.get_backoff(attempt)))
+    }
+
+    /// Returns whether the connection should be re-established to attempt the
+    /// request to `endpoint` once more, after its last attempt resulted in
+    /// `outcome`.
+    pub(crate) fn should_reconnect(
+        &self,
+        endpoint: Endpoint,
+        outcome: std::result::Result<&CCashResponse, &reqwest::Error>,
+    ) -> bool {
+        let may_repeat = endpoint.is_idempotent() || self.retry_non_idempotent;
+        self.reconnect
+            && match outcome {
+                Ok(CCashResponse::Error { code, .. }) =>
+                    may_repeat && matches!(code, 502..=504),
+                Err(e) if e.is_connect() => true,
+                Err(e) if e.is_timeout() => may_repeat,
+                Ok(CCashResponse::Success { .. }) | Err(_) => false,
+            }
+    }
+
+    fn jittered(&self, backoff: Duration) -> Duration {
+        if self.jitter == 0.0 {
+            return backoff;
+        }
+
+        // `RandomState` is randomly seeded, which is random enough for jitter
+        // without depending on a random number generator.
+        #[allow(clippy::cast_precision_loss)]
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+
+        backoff.mul_f64(1.0 - self.jitter * random)
+    }
+}
@@ -1,39 +1,311 @@
 //! This module contains the relevant structures and enums relating to the data
 //! of a `CCash` user.
 
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 use thiserror::Error;
 
-#[derive(Error, Debug, Clone)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 /// Enum to describe the errors that could occur when trying to create a user
 /// with an incorrect username.
 pub enum CCashUsernameError {
     /// Error for if the username provided is too short to meet the
     /// requirements.
-    #[error("CCashUserError: Name too short (needs to be atleast 3 characters)")]
-    NameTooShort,
+    #[error("CCashUserError: Name too short (needs to be at least {0} characters)")]
+    NameTooShort(usize),
     /// Error for if the username provided is too long to meet the requirements.
-    #[error("CCashUserError: Name too long (needs to be at most 16 characters)")]
-    NameTooLong,
+    #[error("CCashUserError: Name too long (needs to be at most {0} characters)")]
+    NameTooLong(usize),
     /// Error for if the username contains illegal characters not allowed in the
     /// requirements.
     #[error("CCashUserError: Name contains invalid characters: {0}")]
     NameContainsInvalidCharacters(String),
 }
 
+/// The rules that a [`Username`] has to follow to be accepted by a `CCash`
+/// instance. The default policy matches the defaults of `CCash`: between 3 and
+/// 16 characters, made of lowercase ASCII letters, digits and underscores.
+/// Instances compiled with different limits can be matched by adjusting the
+/// policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsernamePolicy {
+    min_length: usize,
+    max_length: usize,
+    allowed_characters: String,
+}
+
+impl Default for UsernamePolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 3,
+            max_length: 16,
+            allowed_characters: "abcdefghijklmnopqrstuvwxyz0123456789_".into(),
+        }
+    }
+}
+
+impl UsernamePolicy {
+    /// Constructs the default `UsernamePolicy`.
+    #[must_use]
+    pub fn new() -> UsernamePolicy { UsernamePolicy::default() }
+
+    /// Sets the minimum length of a username, in characters. The maximum
+    /// length is raised to `min_length` if it is shorter.
+    #[must_use]
+    pub fn min_length(mut self, min_length: usize) -> UsernamePolicy {
+        self.min_length = min_length;
+        self.max_length = self.max_length.max(min_length);
+        self
+    }
+
+    /// Sets the maximum length of a username, in characters. The minimum
+    /// length is lowered to `max_length` if it is longer.
+    #[must_use]
+    pub fn max_length(mut self, max_length: usize) -> UsernamePolicy {
+        self.max_length = max_length;
+        self.min_length = self.min_length.min(max_length);
+        self
+    }
+
+    /// Sets the characters that a username can be made of. Usernames are made
+    /// lowercase before they are checked, so uppercase characters are never
+    /// matched.
+    #[must_use]
+    pub fn allowed_characters(mut self, allowed_characters: &str) -> UsernamePolicy {
+        self.allowed_characters = allowed_characters.into();
+        self
+    }
+
+    /// Returns the minimum length of a username, in characters.
+    #[must_use]
+    pub fn get_min_length(&self) -> usize { self.min_length }
+
+    /// Returns the maximum length of a username, in characters.
+    #[must_use]
+    pub fn get_max_length(&self) -> usize { self.max_length }
+
+    /// Returns the characters that a username can be made of.
+    #[must_use]
+    pub fn get_allowed_characters(&self) -> &str { &self.allowed_characters }
+
+    /// Checks `username` against this policy after making it lowercase,
+    /// returning the valid [`Username`].
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`CCashUsernameError`] describing the first rule that
+    /// `username` breaks.
+    pub fn validate(&self, username: &str) -> Result<Username, CCashUsernameError> {
+        // usernames in `CCash` have to be lowercase, so they are converted
+        // rather than rejected.
+        let username = username.to_lowercase();
+        let length = username.chars().count();
+
+        if length < self.min_length {
+            return Err(CCashUsernameError::NameTooShort(self.min_length));
+        } else if length > self.max_length {
+            return Err(CCashUsernameError::NameTooLong(self.max_length));
+        }
+
+        let mut invalid = Vec::new();
+        for c in username.chars() {
+            if !self.allowed_characters.contains(c) && !invalid.contains(&c) {
+                invalid.push(c);
+            }
+        }
+        if !invalid.is_empty() {
+            return Err(CCashUsernameError::NameContainsInvalidCharacters(
+                invalid
+                    .into_iter()
+                    .map(|c| format!("{c:?}"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+        }
+
+        Ok(Username(username))
+    }
+}
+
+/// The name of a `CCash` user that has been checked against a
+/// [`UsernamePolicy`]. Usernames are always lowercase.
+///
+/// A `Username` can be parsed from a string with the default policy, through
+/// [`FromStr`], [`TryFrom`] or `Deserialize`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
+pub struct Username(String);
+
+impl Username {
+    /// Checks `username` against the default [`UsernamePolicy`].
+    ///
+    /// # Errors
+    ///
+    /// See [`UsernamePolicy::validate`].
+    pub fn new(username: &str) -> Result<Username, CCashUsernameError> {
+        UsernamePolicy::default().validate(username)
+    }
+
+    /// Checks `username` against the given `policy`.
+    ///
+    /// # Errors
+    ///
+    /// See [`UsernamePolicy::validate`].
+    pub fn with_policy(
+        username: &str,
+        policy: &UsernamePolicy,
+    ) -> Result<Username, CCashUsernameError> {
+        policy.validate(username)
+    }
+
+    /// Creates a `Username` without checking it. This method is not
+    /// recommended as it could cause hard-to-diagnose or confusing errors from
+    /// `CCash`.
+    #[must_use]
+    pub fn new_unchecked(username: &str) -> Username { Username(username.into()) }
+
+    /// Returns the username as a `&str`.
+    #[must_use]
+    pub fn as_str(&self) -> &str { &self.0 }
+
+    /// Converts the `Username` into a `String`.
+    #[must_use]
+    pub fn into_string(self) -> String { self.0 }
+}
+
+impl FromStr for Username {
+    type Err = CCashUsernameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Username::new(s) }
+}
+
+impl TryFrom<&str> for Username {
+    type Error = CCashUsernameError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> { Username::new(value) }
+}
+
+impl TryFrom<String> for Username {
+    type Error = CCashUsernameError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> { Username::new(&value) }
+}
+
+impl<'de> Deserialize<'de> for Username {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let username = String::deserialize(deserializer)?;
+        Username::new(&username).map_err(serde::de::Error::custom)
+    }
+}
+
+impl AsRef<str> for Username {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+impl fmt::Display for Username {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.0) }
+}
+
+impl PartialEq<str> for Username {
+    fn eq(&self, other: &str) -> bool { self.0 == other }
+}
+
+impl PartialEq<&str> for Username {
+    fn eq(&self, other: &&str) -> bool { self.0 == *other }
+}
+
+/// The password of a [`CCashUser`]. The password is redacted when formatted
+/// with `Debug` or `Display`, and is wiped from memory when dropped. It can
+/// only be read with [`expose_secret`](Password::expose_secret).
+#[derive(Clone, Default)]
+pub struct Password(String);
+
+impl Password {
+    /// Creates a new `Password` from `password`.
+    #[must_use]
+    pub fn new(password: impl Into<String>) -> Password { Password(password.into()) }
+
+    /// Returns the password in plaintext. Take care not to log or otherwise
+    /// persist the returned value.
+    #[must_use]
+    pub fn expose_secret(&self) -> &str { &self.0 }
+
+    /// Returns whether the password is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+impl From<&str> for Password {
+    fn from(password: &str) -> Self { Password::new(password) }
+}
+
+impl From<String> for Password {
+    fn from(password: String) -> Self { Password(password) }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Password(***)")
+    }
+}
+
+impl fmt::Display for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "***") }
+}
+
+impl PartialEq for Password {
+    /// Compares the passwords in constant time with respect to their contents.
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.0.as_bytes(), other.0.as_bytes());
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+}
+
+impl Eq for Password {}
+
+impl Drop for Password {
+    fn drop(&mut self) { zeroize::Zeroize::zeroize(&mut self.0); }
+}
+
+impl<'de> Deserialize<'de> for Password {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Password)
+    }
+}
+
 /// User struct that can be used for authentication purposes.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Hash, PartialEq, Eq)]
+///
+/// The password of the user is held in a [`Password`], so it is redacted when
+/// a `CCashUser` is formatted with `Debug`. Two `CCashUser`s are equal if both
+/// their usernames and passwords are, but only the username is hashed.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
 pub struct CCashUser {
-    #[serde(rename(serialize = "name"))]
     pub(crate) username: String,
-    #[serde(rename(serialize = "pass"))]
-    pub(crate) password: String,
+    pub(crate) password: Password,
+}
+
+impl Hash for CCashUser {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.username.hash(state); }
+}
+
+/// The name and password of a [`CCashUser`], as sent in the body of requests to
+/// `CCash`.
+#[derive(Serialize)]
+pub(crate) struct Credentials<'a> {
+    name: &'a str,
+    pass: &'a str,
 }
 
 impl CCashUser {
     /// Creates a new user where the username is checked against `CCash`'s
-    /// requirements. This is the recommended way to create a `CCashUser` as
-    /// it is guaranteed to not cause `CCash` to reject the username. `username`
-    /// will automatically be made lowercase.
+    /// requirements, as described by the default [`UsernamePolicy`]. This is
+    /// the recommended way to create a `CCashUser` as it is guaranteed to not
+    /// cause `CCash` to reject the username. `username` will automatically be
+    /// made lowercase.
     ///
     /// # Errors
     ///
@@ -42,35 +314,20 @@ impl CCashUser {
     /// - Returns `CCashUsernameError::NameTooLong` if given `username` is
     ///   longer than 16 characters.
     /// - Returns `CCashUsernameError::NameContainsInvalidCharacters` if given
-    ///   `username` otherwise contains invalid characters such as spaces.
+    ///   `username` contains characters other than ASCII letters, digits and
+    ///   underscores.
     pub fn new(username: &str, password: &str) -> Result<Self, CCashUsernameError> {
-        let username = username.to_lowercase(); // usernames in `CCash` have to be lowercase. there's no point in iterating
-        // through all the characters and checking, then erroring if a uppercase
-        // character is found when an automatic conversion will
-        // suffice.
-
-        if username.len() < 3 {
-            return Err(CCashUsernameError::NameTooShort);
-        } else if username.len() > 16 {
-            return Err(CCashUsernameError::NameTooLong);
-        } else if username.contains(' ') {
-            return Err(CCashUsernameError::NameContainsInvalidCharacters(
-                "Name cannot contain spaces".into(),
-            ));
-        } else if !username
-            .chars()
-            .filter(|c| *c != '_')
-            .all(char::is_alphanumeric)
-        {
-            return Err(CCashUsernameError::NameContainsInvalidCharacters(
-                "Name cannot contain non-alphanumeric characters".into(),
-            ));
-        }
+        Ok(Self::from_username(Username::new(username)?, password))
+    }
 
-        Ok(Self {
-            username,
-            password: password.replace(' ', ""),
-        })
+    /// Creates a new user from a `username` that has already been checked, for
+    /// instance against a custom [`UsernamePolicy`].
+    #[must_use]
+    pub fn from_username(username: Username, password: &str) -> Self {
+        Self {
+            username: username.into_string(),
+            password: Password(password.replace(' ', "")),
+        }
     }
 
     /// Creates a new `CCashUser` with an unchecked username against `CCash`'s
@@ -80,7 +337,7 @@ impl CCashUser {
     pub fn new_unchecked(username: &str, password: &str) -> Self {
         Self {
             username: username.into(),
-            password: password.into(),
+            password: Password::new(password),
         }
     }
 
@@ -89,9 +346,42 @@ impl CCashUser {
     pub fn get_username(&self) -> &str { &self.username }
     /// Returns an immutable reference to the `CCashUser`'s password.
     #[must_use]
-    pub fn get_password(&self) -> &str { &self.password }
+    #[deprecated(
+        since = "2.0.0",
+        note = "Please use `expose_password` or `get_password_secret`"
+    )]
+    pub fn get_password(&self) -> &str { self.password.expose_secret() }
+    /// Returns the `CCashUser`'s password in plaintext. Take care not to log or
+    /// otherwise persist the returned value.
+    #[must_use]
+    pub fn expose_password(&self) -> &str { self.password.expose_secret() }
+    /// Returns an immutable reference to the `CCashUser`'s [`Password`].
+    #[must_use]
+    pub fn get_password_secret(&self) -> &Password { &self.password }
+
+    pub(crate) fn credentials(&self) -> Credentials<'_> {
+        Credentials {
+            name: &self.username,
+            pass: self.password.expose_secret(),
+        }
+    }
 
     pub(crate) fn update_password(&mut self, new_password: &str) {
-        self.password = new_password.into();
+        self.password = Password::new(new_password);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_length_limits_ordered() {
+        let policy = UsernamePolicy::new().min_length(20);
+        assert_eq!((policy.get_min_length(), policy.get_max_length()), (20, 20));
+
+        let policy = UsernamePolicy::new().max_length(2);
+        assert_eq!((policy.get_min_length(), policy.get_max_length()), (2, 2));
+        assert!(policy.validate("ab").is_ok());
     }
 }
@@ -0,0 +1,349 @@
+//! This module contains streams that poll a `CCash` instance and yield events
+//! when the account of a user changes, for bots and dashboards that need to
+//! react to payments without polling by hand.
+//!
+//! ```no_run
+//! # async fn run() -> ccash_rs::Result<()> {
+//! use ccash_rs::{watch::*, *};
+//! use futures::{pin_mut, StreamExt};
+//! use std::time::Duration;
+//!
+//! let mut session = CCashSession::new("https://bank.example.com");
+//! session.establish_connection().await?;
+//! let shop = CCashUser::new("shop", "password")?;
+//!
+//! let events = watch_transactions(&session, &shop, Duration::from_secs(5));
+//! pin_mut!(events);
+//!
+//! while let Some(event) = events.next().await {
+//!     match event? {
+//!         TransactionEvent::Transaction(log) if log.get_if_receiving() => {
+//!             println!("{} paid {}", log.get_counterparty(), log.get_amount());
+//!         },
+//!         TransactionEvent::Transaction(_) => {},
+//!         TransactionEvent::Gap => println!("some transactions may have been missed"),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    methods, CCashError, CCashSession, CCashUser, Csh, Result, TransactionLogV2,
+};
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream};
+use std::{collections::VecDeque, time::Duration};
+
+/// Enum for the events yielded by [`watch_transactions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionEvent {
+    /// A transaction that was not in the logs of the user when they were last
+    /// polled.
+    Transaction(TransactionLogV2),
+    /// More transactions were made between two polls than fit in the `max_log`
+    /// window of the `CCash` instance, so some transactions may have been
+    /// missed. The transactions that are still in the logs are yielded after
+    /// this event.
+    Gap,
+}
+
+struct TransactionWatcher<'a> {
+    session: &'a CCashSession,
+    user: CCashUser,
+    interval: Duration,
+    max_log: Option<usize>,
+    has_polled: bool,
+    seen: Option<Vec<TransactionLogV2>>,
+    pending: VecDeque<TransactionEvent>,
+}
+
+impl TransactionWatcher<'_> {
+    /// Compares the `logs` with the logs seen on the last poll and queues the
+    /// new entries, oldest first.
+    fn update(&mut self, logs: Vec<TransactionLogV2>) {
+        let Some(seen) = self.seen.take() else {
+            self.seen = Some(logs);
+            return;
+        };
+
+        let new = count_new_logs(&seen, &logs);
+        let is_gap = new > 0
+            && new == logs.len()
+            && (!seen.is_empty() || self.max_log.is_some_and(|max| logs.len() >= max));
+        if is_gap {
+            self.pending.push_back(TransactionEvent::Gap);
+        }
+
+        self.pending.extend(
+            logs[..new]
+                .iter()
+                .rev()
+                .cloned()
+                .map(TransactionEvent::Transaction),
+        );
+        self.seen = Some(logs);
+    }
+}
+
+/// Returns the amount of entries at the start of `logs` that are not in `seen`,
+/// where both are logs of the same user in the order returned by `CCash`.
+pub(crate) fn count_new_logs(
+    seen: &[TransactionLogV2],
+    logs: &[TransactionLogV2],
+) -> usize {
+    // `CCash` returns the newest transactions first, so the logs are the new
+    // entries followed by the start of the seen logs, minus the entries that
+    // were pushed out of the `max_log` window. The smallest amount of new
+    // entries that lines up with the seen logs is used, so identical entries
+    // made in the same second are only counted once they can no longer be
+    // confused with entries that were already seen.
+    (0..=logs.len())
+        .find(|&new| {
+            let overlap = logs.len() - new;
+            overlap <= seen.len() && logs[new..] == seen[..overlap]
+        })
+        .unwrap_or(logs.len())
+}
+
+/// Returns a [`Stream`] that polls the logs of the [`user`](CCashUser) every
+/// `interval` and yields the transactions that were not in the logs on the
+/// previous poll, oldest first. Transactions made before the stream is first
+/// polled are not yielded.
+///
+/// If more transactions are made between two polls than the `max_log` window
+/// of the `CCash` instance can hold, a [`TransactionEvent::Gap`] is yielded
+/// before the transactions that are still in the logs. The `max_log` window is
+/// read from the [properties](CCashSession::get_properties) of the `session`
+/// when this function is called.
+///
+/// Transactions are told apart by comparing the logs between polls, so a
+/// transaction identical to one already seen in the same second may only be
+/// detected once a different transaction is made, if the logs are already
+/// full.
+///
+/// The stream never ends. If a poll fails, the error is yielded and the next
+/// poll is made after another `interval`.
+///
+/// # Errors
+///
+/// Yields a [`CCashError`] for the same reasons as
+/// [`methods::get_logs`].
+pub fn watch_transactions<'a>(
+    session: &'a CCashSession,
+    user: &CCashUser,
+    interval: Duration,
+) -> impl Stream<Item = Result<TransactionEvent>> + 'a {
+    let watcher = TransactionWatcher {
+        session,
+        user: user.clone(),
+        interval,
+        max_log: session
+            .get_properties()
+            .as_ref()
+            .map(|p| p.get_max_log() as usize),
+        has_polled: false,
+        seen: None,
+        pending: VecDeque::new(),
+    };
+
+    stream::unfold(watcher, |mut watcher| async move {
+        loop {
+            if let Some(event) = watcher.pending.pop_front() {
+                return Some((Ok(event), watcher));
+            }
+
+            if watcher.has_polled {
+                tokio::time::sleep(watcher.interval).await;
+            }
+            watcher.has_polled = true;
+
+            match methods::get_logs(watcher.session, &watcher.user).await {
+                Ok(logs) => watcher.update(logs),
+                Err(e) => return Some((Err(e), watcher)),
+            }
+        }
+    })
+}
+
+/// A change in the balance of a user, detected by [`watch_balance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceChanged {
+    /// The balance of the user on the previous successful poll.
+    pub old: Csh,
+    /// The balance of the user now.
+    pub new: Csh,
+    /// The difference between the `new` and `old` balance.
+    pub delta: i64,
+    /// The time the change was detected at.
+    pub at: DateTime<Utc>,
+}
+
+/// Enum for the events yielded by [`watch_balance`].
+#[derive(Debug)]
+pub enum BalanceEvent {
+    /// The balance of the user on the first successful poll.
+    Initial {
+        /// The balance of the user.
+        balance: Csh,
+        /// The time the balance was polled at.
+        at: DateTime<Utc>,
+    },
+    /// The balance of the user changed since the previous successful poll.
+    Changed(BalanceChanged),
+    /// The `CCash` instance could not be reached. Polling carries on, and
+    /// [`BalanceEvent::Reconnected`] is yielded once it can be reached again.
+    ConnectionLost(CCashError),
+    /// The `CCash` instance can be reached again after a
+    /// [`BalanceEvent::ConnectionLost`]. If the balance changed in the
+    /// meantime, a [`BalanceEvent::Changed`] follows.
+    Reconnected,
+}
+
+/// Struct that describes how often [`watch_balance`] polls the balance. After
+/// every poll where the balance is unchanged, the interval is multiplied by the
+/// multiplier up to the max interval, and it is reset once the balance
+/// changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceWatchOptions {
+    interval: Duration,
+    max_interval: Duration,
+    multiplier: f64,
+}
+
+impl BalanceWatchOptions {
+    /// Returns `BalanceWatchOptions` that poll every `interval`, backing off
+    /// by a factor of 1.5 up to 8 times the `interval` while the balance is
+    /// unchanged.
+    #[must_use]
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            max_interval: interval.saturating_mul(8),
+            multiplier: 1.5,
+        }
+    }
+
+    /// Sets the max interval between two polls.
+    #[must_use]
+    pub fn with_max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval.max(self.interval);
+        self
+    }
+
+    /// Sets the factor the interval is multiplied by after every poll where
+    /// the balance is unchanged. A `multiplier` of `1.0` disables the backoff.
+    #[must_use]
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    fn next_interval(&self, interval: Duration) -> Duration {
+        interval.mul_f64(self.multiplier).min(self.max_interval)
+    }
+}
+
+struct BalanceWatcher<'a> {
+    session: &'a CCashSession,
+    user: CCashUser,
+    options: BalanceWatchOptions,
+    interval: Duration,
+    has_polled: bool,
+    is_connection_lost: bool,
+    balance: Option<Csh>,
+    pending: VecDeque<BalanceEvent>,
+}
+
+impl BalanceWatcher<'_> {
+    fn update(&mut self, result: Result<Csh>) -> Option<CCashError> {
+        let balance = match result {
+            Ok(balance) => balance,
+            Err(e) if e.is_connection_error() => {
+                self.interval = self.options.next_interval(self.interval);
+                if !self.is_connection_lost {
+                    self.is_connection_lost = true;
+                    self.pending.push_back(BalanceEvent::ConnectionLost(e));
+                }
+                return None;
+            },
+            Err(e) => return Some(e),
+        };
+
+        if self.is_connection_lost {
+            self.is_connection_lost = false;
+            self.pending.push_back(BalanceEvent::Reconnected);
+        }
+
+        let at = Utc::now();
+        match self.balance.replace(balance) {
+            None => self
+                .pending
+                .push_back(BalanceEvent::Initial { balance, at }),
+            Some(old) if old != balance => {
+                self.interval = self.options.interval;
+                self.pending
+                    .push_back(BalanceEvent::Changed(BalanceChanged {
+                        old,
+                        new: balance,
+                        delta: old.delta_to(balance),
+                        at,
+                    }));
+            },
+            Some(_) => self.interval = self.options.next_interval(self.interval),
+        }
+
+        None
+    }
+}
+
+/// Returns a [`Stream`] that polls the balance of the [`user`](CCashUser) and
+/// yields a [`BalanceEvent::Initial`] with the first balance, followed by a
+/// [`BalanceEvent::Changed`] whenever the balance changes. The polling interval
+/// is described by the `options`.
+///
+/// If the `CCash` instance cannot be reached, a
+/// [`BalanceEvent::ConnectionLost`] is yielded and polling carries on, backing
+/// off as if the balance were unchanged, until a [`BalanceEvent::Reconnected`]
+/// is yielded.
+///
+/// The stream never ends.
+///
+/// # Errors
+///
+/// Yields a [`CCashError`] for the same reasons as [`methods::get_balance`],
+/// except for errors caused by the `CCash` instance being unreachable.
+pub fn watch_balance<'a>(
+    session: &'a CCashSession,
+    user: &CCashUser,
+    options: BalanceWatchOptions,
+) -> impl Stream<Item = Result<BalanceEvent>> + 'a {
+    let watcher = BalanceWatcher {
+        session,
+        user: user.clone(),
+        interval: options.interval,
+        options,
+        has_polled: false,
+        is_connection_lost: false,
+        balance: None,
+        pending: VecDeque::new(),
+    };
+
+    stream::unfold(watcher, |mut watcher| async move {
+        loop {
+            if let Some(event) = watcher.pending.pop_front() {
+                return Some((Ok(event), watcher));
+            }
+
+            if watcher.has_polled {
+                tokio::time::sleep(watcher.interval).await;
+            }
+            watcher.has_polled = true;
+
+            let result = methods::get_balance(watcher.session, &watcher.user).await;
+            if let Some(e) = watcher.update(result) {
+                return Some((Err(e), watcher));
+            }
+        }
+    })
+}
@@ -0,0 +1,60 @@
+use ccash_rs::{
+    mock::{MockConfig, MockFault, MockServer},
+    *,
+};
+
+async fn logs_from(version: Option<u32>) -> Result<Vec<TransactionLogV2>> {
+    let mock = MockServer::start_with(MockConfig {
+        version,
+        ..Default::default()
+    })
+    .await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+
+    let mut session = CCashSession::new(&mock.url());
+    session.establish_connection().await?;
+    let bank: &dyn CCashApi = &session;
+    let alice = CCashUser::new("alice", "password")?;
+
+    bank.send_funds(&alice, "bob", Csh::new(25)).await?;
+    bank.get_logs(&alice).await
+}
+
+#[tokio::test]
+async fn gets_logs_from_the_best_endpoint() {
+    for version in [Some(1), Some(2), None] {
+        let logs = logs_from(version).await.unwrap();
+
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].get_counterparty(), "bob");
+        assert!(!logs[0].get_if_receiving());
+        assert_eq!(logs[0].get_amount(), 25);
+    }
+}
+
+#[tokio::test]
+async fn attempts_v2_logs_when_the_version_is_unknown() {
+    let mock = MockServer::start_with(MockConfig {
+        version: None,
+        ..Default::default()
+    })
+    .await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+
+    let mut session = CCashSession::new(&mock.url());
+    session.establish_connection().await.unwrap();
+    let alice = CCashUser::new("alice", "password").unwrap();
+    methods::send_funds(&session, &alice, "bob", 25)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        methods::get_log_v2(&session, &alice).await.unwrap().len(),
+        1
+    );
+
+    mock.inject_fault("/v2/user/log", MockFault::Reject(404));
+    assert_eq!(methods::get_logs(&session, &alice).await.unwrap().len(), 1);
+}
@@ -0,0 +1,79 @@
+use ccash_rs::{
+    blocking::{methods, BlockingCCashSession},
+    mock::MockServer,
+    query::{LogQuery, TransactionDirection},
+    *,
+};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// Starts a `MockServer` on its own runtime, as the blocking client must not be
+/// used from within one.
+fn start() -> (Runtime, MockServer) {
+    let runtime = Runtime::new().unwrap();
+    let mock = runtime.block_on(MockServer::start());
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 50);
+    (runtime, mock)
+}
+
+#[test]
+fn mirrors_the_async_methods() {
+    let (_runtime, mock) = start();
+    let mut session = BlockingCCashSession::builder(&mock.url())
+        .timeout(Duration::from_secs(5))
+        .user_agent("ccash-rs-tests")
+        .build();
+    session.establish_connection().unwrap();
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    assert_eq!(methods::get_balance(&session, &alice).unwrap(), 100);
+    assert_eq!(
+        methods::send_funds(&session, &alice, "bob", 30).unwrap(),
+        70
+    );
+    assert_eq!(methods::send_funds(&session, &alice, "bob", 5).unwrap(), 65);
+    assert_eq!(mock.balance("bob"), Some(Csh::new(85)));
+
+    let logs = methods::get_log_v2_filtered(
+        &session,
+        &alice,
+        &LogQuery::new()
+            .direction(TransactionDirection::Sent)
+            .min_amount(10),
+    )
+    .unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].get_amount(), 30);
+
+    let history = methods::get_balance_history(&session, &alice).unwrap();
+    let balances = history
+        .get_points()
+        .iter()
+        .map(|p| p.get_balance_after())
+        .collect::<Vec<_>>();
+    assert_eq!(balances, [70, 65]);
+    assert_eq!(history.get_opening_balance(), 100);
+    assert!(history.is_consistent());
+}
+
+#[test]
+fn reestablishes_a_session_that_is_not_connected() {
+    let (_runtime, mock) = start();
+    let session = BlockingCCashSession::new(&mock.url());
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    assert_eq!(methods::get_balance(&session, &alice).unwrap(), 100);
+    assert!(session.is_connected());
+    assert!(session.get_properties().is_some());
+}
+
+#[test]
+fn uses_the_api_prefix() {
+    let (_runtime, mock) = start();
+    let mut session = BlockingCCashSession::builder(&mock.url())
+        .api_prefix("/not-the-api")
+        .build();
+
+    assert!(session.establish_connection().is_err());
+}
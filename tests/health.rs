@@ -0,0 +1,45 @@
+use ccash_rs::{
+    health::*,
+    mock::{MockFault, MockServer},
+    *,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::RwLock;
+
+const PROPERTIES: &str = "/properties";
+const INTERVAL: Duration = Duration::from_secs(5);
+
+async fn next_status(
+    status: &mut tokio::sync::watch::Receiver<HealthStatus>,
+) -> HealthStatus {
+    tokio::time::timeout(INTERVAL, status.changed())
+        .await
+        .unwrap()
+        .unwrap();
+    status.borrow_and_update().clone()
+}
+
+#[tokio::test]
+async fn marks_the_session_as_disconnected_while_down() {
+    let mock = MockServer::start().await;
+    let mut session = CCashSession::new(&mock.url());
+    session.establish_connection().await.unwrap();
+    let session = Arc::new(RwLock::new(session));
+
+    let monitor = HealthMonitor::spawn(Arc::clone(&session), INTERVAL);
+    let mut status = monitor.subscribe();
+    assert!(next_status(&mut status).await.is_up());
+
+    mock.inject_fault(PROPERTIES, MockFault::Reject(503));
+    mock.inject_fault(PROPERTIES, MockFault::Reject(503));
+    monitor.check_now();
+    assert!(matches!(
+        next_status(&mut status).await,
+        HealthStatus::Down { .. }
+    ));
+    assert!(!session.read().await.is_connected());
+
+    monitor.check_now();
+    assert!(next_status(&mut status).await.is_up());
+    assert!(session.read().await.is_connected());
+}
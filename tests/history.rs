@@ -0,0 +1,87 @@
+use ccash_rs::{mock::MockServer, *};
+
+async fn connect(mock: &MockServer) -> CCashSession {
+    let mut session = CCashSession::new(&mock.url());
+    session.establish_connection().await.unwrap();
+    session
+}
+
+#[tokio::test]
+async fn reconstructs_balance_from_logs() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 50);
+    let session = connect(&mock).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+    let bob = CCashUser::new("bob", "password").unwrap();
+
+    methods::send_funds(&session, &alice, "bob", 30)
+        .await
+        .unwrap();
+    methods::send_funds(&session, &bob, "alice", 5)
+        .await
+        .unwrap();
+
+    let history = methods::get_balance_history(&session, &alice)
+        .await
+        .unwrap();
+    let balances = history
+        .get_points()
+        .iter()
+        .map(|p| p.get_balance_after())
+        .collect::<Vec<_>>();
+
+    assert_eq!(balances, [70, 75]);
+    assert_eq!(history.get_current_balance(), 75);
+    assert_eq!(history.get_opening_balance(), 100);
+    assert!(!history.is_truncated());
+    assert!(!history.has_unlogged_adjustments());
+    assert!(history.is_consistent());
+}
+
+#[tokio::test]
+async fn flags_truncated_history() {
+    let mock = MockServer::start_with(mock::MockConfig {
+        max_log: 2,
+        ..Default::default()
+    })
+    .await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+    let session = connect(&mock).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    for _ in 0..3 {
+        methods::send_funds(&session, &alice, "bob", 10)
+            .await
+            .unwrap();
+    }
+
+    let history = methods::get_balance_history(&session, &alice)
+        .await
+        .unwrap();
+
+    assert_eq!(history.get_points().len(), 2);
+    assert_eq!(history.get_opening_balance(), 90);
+    assert!(history.is_truncated());
+}
+
+#[tokio::test]
+async fn flags_unlogged_adjustments() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+    let session = connect(&mock).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    methods::send_funds(&session, &alice, "bob", 100)
+        .await
+        .unwrap();
+    mock.set_balance("bob", 0);
+
+    let bob = CCashUser::new("bob", "password").unwrap();
+    let history = methods::get_balance_history(&session, &bob).await.unwrap();
+
+    assert_eq!(history.get_opening_balance(), -100);
+    assert!(history.has_unlogged_adjustments());
+}
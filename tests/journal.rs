@@ -0,0 +1,207 @@
+use ccash_rs::{
+    journal::*,
+    mock::{MockConfig, MockFault, MockServer},
+    *,
+};
+use std::path::PathBuf;
+
+const TRANSFER: &str = "/v1/user/transfer";
+
+async fn start(max_log: u32) -> (MockServer, CCashSession) {
+    let mock = MockServer::start_with(MockConfig {
+        max_log,
+        ..Default::default()
+    })
+    .await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+
+    let mut session = CCashSession::new(&mock.url());
+    session.establish_connection().await.unwrap();
+    (mock, session)
+}
+
+fn alice() -> CCashUser { CCashUser::new("alice", "password").unwrap() }
+
+fn open_journal(name: &str) -> (PathBuf, TransferJournal) {
+    let path = std::env::temp_dir().join(format!(
+        "ccash-journal-{}-{name}.ndjson",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let journal = TransferJournal::open(&path).unwrap();
+    (path, journal)
+}
+
+/// Makes every attempt to send the transfer with `key` fail without reaching
+/// the instance, leaving it pending in the `journal`.
+async fn leave_pending(
+    mock: &MockServer,
+    session: &CCashSession,
+    journal: &mut TransferJournal,
+    key: &str,
+) {
+    for _ in 0..3 {
+        mock.inject_fault(TRANSFER, MockFault::Reject(503));
+    }
+
+    let result = send_funds_idempotent(session, journal, &alice(), key, "bob", 25).await;
+    assert!(matches!(result, Err(JournalError::CCash(_))));
+    assert_eq!(
+        journal.get(key).unwrap().get_state(),
+        &TransferState::Pending
+    );
+}
+
+#[tokio::test]
+async fn sends_once_per_key() {
+    let (mock, session) = start(100).await;
+    let (_, mut journal) = open_journal("sends_once_per_key");
+
+    let outcome = send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25)
+        .await
+        .unwrap();
+    assert_eq!(
+        outcome,
+        TransferOutcome::Sent {
+            balance: Csh::new(75)
+        }
+    );
+
+    let outcome = send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25)
+        .await
+        .unwrap();
+    assert_eq!(
+        outcome,
+        TransferOutcome::AlreadyCompleted {
+            balance: Csh::new(75)
+        }
+    );
+
+    let result =
+        send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 30).await;
+    assert!(matches!(result, Err(JournalError::KeyReused(_))));
+    assert_eq!(mock.balance("bob"), Some(Csh::new(25)));
+}
+
+#[tokio::test]
+async fn reconciles_lost_responses() {
+    let (mock, session) = start(100).await;
+    let (_, mut journal) = open_journal("reconciles_lost_responses");
+
+    mock.inject_fault(TRANSFER, MockFault::LoseResponse(504));
+    let outcome = send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        outcome,
+        TransferOutcome::Reconciled {
+            balance: Csh::new(75)
+        }
+    );
+    assert_eq!(mock.balance("bob"), Some(Csh::new(25)));
+}
+
+#[tokio::test]
+async fn resends_transfers_that_did_not_land() {
+    let (mock, session) = start(100).await;
+    let (_, mut journal) = open_journal("resends_transfers_that_did_not_land");
+
+    mock.inject_fault(TRANSFER, MockFault::Reject(503));
+    let outcome = send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        outcome,
+        TransferOutcome::Sent {
+            balance: Csh::new(75)
+        }
+    );
+    assert_eq!(mock.balance("bob"), Some(Csh::new(25)));
+}
+
+#[tokio::test]
+async fn resends_pending_transfers_after_reopening() {
+    let (mock, session) = start(100).await;
+    let (path, mut journal) = open_journal("resends_pending_transfers_after_reopening");
+
+    leave_pending(&mock, &session, &mut journal, "a").await;
+    let mut journal = TransferJournal::open(&path).unwrap();
+    assert_eq!(journal.pending().count(), 1);
+
+    let outcome = send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        outcome,
+        TransferOutcome::Sent {
+            balance: Csh::new(75)
+        }
+    );
+    assert_eq!(mock.balance("bob"), Some(Csh::new(25)));
+}
+
+#[tokio::test]
+async fn does_not_resend_without_logs() {
+    let (mock, session) = start(0).await;
+    let (_, mut journal) = open_journal("does_not_resend_without_logs");
+
+    mock.inject_fault(TRANSFER, MockFault::LoseResponse(504));
+    let result =
+        send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25).await;
+
+    assert!(matches!(result, Err(JournalError::Unresolved(_))));
+    assert_eq!(
+        journal.get("a").unwrap().get_state(),
+        &TransferState::Pending
+    );
+    assert_eq!(mock.balance("bob"), Some(Csh::new(25)));
+}
+
+#[tokio::test]
+async fn does_not_resend_after_unlogged_debit() {
+    let (mock, session) = start(100).await;
+    let (_, mut journal) = open_journal("does_not_resend_after_unlogged_debit");
+
+    leave_pending(&mock, &session, &mut journal, "a").await;
+    mock.set_balance("alice", 50);
+    let result =
+        send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25).await;
+
+    assert!(matches!(result, Err(JournalError::Unresolved(_))));
+    assert_eq!(mock.balance("bob"), Some(Csh::ZERO));
+}
+
+#[tokio::test]
+async fn does_not_resend_after_logs_overflow() {
+    let (mock, session) = start(2).await;
+    let (_, mut journal) = open_journal("does_not_resend_after_logs_overflow");
+
+    leave_pending(&mock, &session, &mut journal, "a").await;
+    mock.push_log("alice", "carol", true, 10, 0);
+    mock.push_log("alice", "carol", true, 15, 0);
+    let result =
+        send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25).await;
+
+    assert!(matches!(result, Err(JournalError::Unresolved(_))));
+    assert_eq!(mock.balance("bob"), Some(Csh::ZERO));
+}
+
+#[tokio::test]
+async fn does_not_resend_when_identical_logs_fill_the_window() {
+    let (mock, session) = start(2).await;
+    let (_, mut journal) =
+        open_journal("does_not_resend_when_identical_logs_fill_the_window");
+
+    mock.push_log("alice", "bob", false, 25, 0);
+    mock.push_log("alice", "bob", false, 25, 0);
+    mock.inject_fault(TRANSFER, MockFault::Reject(503));
+    let result =
+        send_funds_idempotent(&session, &mut journal, &alice(), "a", "bob", 25).await;
+
+    assert!(matches!(result, Err(JournalError::Unresolved(_))));
+    assert_eq!(mock.balance("bob"), Some(Csh::ZERO));
+}
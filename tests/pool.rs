@@ -0,0 +1,81 @@
+use ccash_rs::{
+    mock::{MockFault, MockServer},
+    pool::*,
+    *,
+};
+use std::time::Duration;
+
+const BALANCE: &str = "/v1/user/balance";
+
+/// Starts a primary and a standby instance, where alice has a balance of 100 on
+/// the primary and 90 on the standby, and connects a pool to both.
+async fn start(pool: CCashPool) -> (MockServer, MockServer, CCashPool) {
+    let primary = MockServer::start().await;
+    primary.add_user("alice", "password", 100);
+    let standby = MockServer::start().await;
+    standby.add_user("alice", "password", 90);
+
+    let session = |mock: &MockServer| {
+        CCashSession::new(&mock.url()).with_retry_policy(RetryPolicy::none())
+    };
+    let mut pool = pool
+        .with_instance("primary", session(&primary))
+        .with_instance("standby", session(&standby));
+    pool.set_standby("primary", "standby").unwrap();
+    assert!(pool.connect_all().await.values().all(Result::is_ok));
+
+    (primary, standby, pool)
+}
+
+fn alice() -> CCashUser { CCashUser::new("alice", "password").unwrap() }
+
+#[tokio::test]
+async fn fails_over_to_the_standby() {
+    let (primary, _standby, pool) = start(CCashPool::new()).await;
+    drop(primary);
+
+    assert_eq!(pool.get_balance("primary", &alice()).await.unwrap(), 90);
+    assert!(pool.get_health("primary").unwrap().is_unhealthy());
+    assert!(!pool.get_health("standby").unwrap().is_unhealthy());
+}
+
+#[tokio::test]
+async fn calls_the_standby_first_while_the_primary_is_unhealthy() {
+    let (primary, standby, pool) = start(CCashPool::new()).await;
+    primary.inject_fault(BALANCE, MockFault::Reject(503));
+
+    assert_eq!(pool.get_balance("primary", &alice()).await.unwrap(), 90);
+    assert!(pool.get_health("primary").unwrap().is_unhealthy());
+
+    let (primary_requests, standby_requests) =
+        (primary.request_count(), standby.request_count());
+    assert_eq!(pool.get_balance("primary", &alice()).await.unwrap(), 90);
+    assert_eq!(primary.request_count(), primary_requests);
+    assert_eq!(standby.request_count() - standby_requests, 1);
+}
+
+#[tokio::test]
+async fn fails_back_to_the_primary_once_it_responds() {
+    let (primary, _standby, pool) =
+        start(CCashPool::new().with_failback_delay(Duration::ZERO)).await;
+    primary.inject_fault(BALANCE, MockFault::Reject(503));
+
+    assert_eq!(pool.get_balance("primary", &alice()).await.unwrap(), 90);
+    assert!(pool.get_health("primary").unwrap().is_unhealthy());
+
+    assert_eq!(pool.get_balance("primary", &alice()).await.unwrap(), 100);
+    assert!(matches!(
+        pool.get_health("primary").unwrap(),
+        InstanceHealth::Healthy { .. }
+    ));
+}
+
+#[tokio::test]
+async fn does_not_fail_over_rejected_calls() {
+    let (_primary, standby, pool) = start(CCashPool::new()).await;
+    let wrong = CCashUser::new("alice", "wrong").unwrap();
+    let requests = standby.request_count();
+
+    assert!(!pool.verify_password("primary", &wrong).await.unwrap());
+    assert_eq!(standby.request_count(), requests);
+}
@@ -0,0 +1,198 @@
+use ccash_rs::{
+    mock::{MockFault, MockServer},
+    *,
+};
+use std::time::Duration;
+
+const BALANCE: &str = "/v1/user/balance";
+const TRANSFER: &str = "/v1/user/transfer";
+
+async fn connect(mock: &MockServer, retry_policy: RetryPolicy) -> CCashSession {
+    let mut session = CCashSession::new(&mock.url()).with_retry_policy(retry_policy);
+    session.establish_connection().await.unwrap();
+    session
+}
+
+fn retry_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::exponential(max_attempts)
+        .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+}
+
+#[tokio::test]
+async fn retries_idempotent_requests() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = connect(&mock, retry_policy(3)).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    mock.inject_fault(BALANCE, MockFault::Reject(503));
+    mock.inject_fault(BALANCE, MockFault::Reject(502));
+    let requests = mock.request_count();
+
+    assert_eq!(methods::get_balance(&session, &alice).await.unwrap(), 100);
+    assert_eq!(mock.request_count() - requests, 3);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_attempts() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = connect(&mock, retry_policy(2).with_reconnect(false)).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    for _ in 0..3 {
+        mock.inject_fault(BALANCE, MockFault::Reject(503));
+    }
+    let requests = mock.request_count();
+
+    assert!(methods::get_balance(&session, &alice).await.is_err());
+    assert_eq!(mock.request_count() - requests, 2);
+}
+
+#[tokio::test]
+async fn reconnects_after_max_attempts() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = connect(&mock, retry_policy(2)).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    mock.inject_fault(BALANCE, MockFault::Reject(503));
+    mock.inject_fault(BALANCE, MockFault::Reject(503));
+    let requests = mock.request_count();
+
+    assert_eq!(methods::get_balance(&session, &alice).await.unwrap(), 100);
+    assert_eq!(mock.request_count() - requests, 4);
+}
+
+#[tokio::test]
+async fn reconnects_once() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = connect(&mock, retry_policy(1)).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    for _ in 0..3 {
+        mock.inject_fault(BALANCE, MockFault::Reject(503));
+    }
+    let requests = mock.request_count();
+
+    assert!(methods::get_balance(&session, &alice).await.is_err());
+    assert_eq!(mock.request_count() - requests, 3);
+}
+
+#[tokio::test]
+async fn does_not_attempt_again_if_reconnecting_fails() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = connect(&mock, retry_policy(1)).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    mock.inject_fault(BALANCE, MockFault::Reject(503));
+    mock.inject_fault("/properties", MockFault::Reject(503));
+    let requests = mock.request_count();
+
+    assert!(methods::get_balance(&session, &alice).await.is_err());
+    assert_eq!(mock.request_count() - requests, 2);
+}
+
+#[tokio::test]
+async fn does_not_retry_without_a_policy() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = connect(&mock, RetryPolicy::none()).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    mock.inject_fault(BALANCE, MockFault::Reject(503));
+    let requests = mock.request_count();
+
+    assert!(methods::get_balance(&session, &alice).await.is_err());
+    assert_eq!(mock.request_count() - requests, 1);
+}
+
+#[tokio::test]
+async fn does_not_retry_non_idempotent_requests() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+    let session = connect(&mock, retry_policy(3)).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    mock.inject_fault(TRANSFER, MockFault::LoseResponse(504));
+    let requests = mock.request_count();
+
+    assert!(methods::send_funds(&session, &alice, "bob", 25)
+        .await
+        .is_err());
+    assert_eq!(mock.request_count() - requests, 1);
+    assert_eq!(mock.balance("bob"), Some(Csh::new(25)));
+}
+
+#[tokio::test]
+async fn retries_non_idempotent_requests_when_opted_in() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+    let session = connect(&mock, retry_policy(3).with_non_idempotent_retries(true)).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    mock.inject_fault(TRANSFER, MockFault::Reject(503));
+
+    assert_eq!(
+        methods::send_funds(&session, &alice, "bob", 25)
+            .await
+            .unwrap(),
+        75
+    );
+    assert_eq!(mock.balance("bob"), Some(Csh::new(25)));
+}
+
+#[tokio::test]
+async fn does_not_retry_rejected_requests() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 10);
+    mock.add_user("bob", "password", 0);
+    let session = connect(&mock, retry_policy(3).with_non_idempotent_retries(true)).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+    let requests = mock.request_count();
+
+    assert!(methods::send_funds(&session, &alice, "bob", 25)
+        .await
+        .is_err());
+    assert_eq!(mock.request_count() - requests, 1);
+    assert_eq!(mock.balance("alice"), Some(Csh::new(10)));
+}
+
+#[tokio::test]
+async fn keeps_the_reestablished_connection() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = CCashSession::new(&mock.url());
+    let alice = CCashUser::new("alice", "password").unwrap();
+    assert!(!session.is_connected());
+
+    let requests = mock.request_count();
+    assert_eq!(methods::get_balance(&session, &alice).await.unwrap(), 100);
+    assert_eq!(mock.request_count() - requests, 2);
+    assert!(session.is_connected());
+    assert_eq!(session.get_properties().unwrap().get_max_log(), 100);
+
+    let requests = mock.request_count();
+    assert_eq!(methods::get_balance(&session, &alice).await.unwrap(), 100);
+    assert_eq!(mock.request_count() - requests, 1);
+}
+
+#[tokio::test]
+async fn does_not_reestablish_without_a_policy() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = CCashSession::new(&mock.url()).with_retry_policy(RetryPolicy::none());
+    let alice = CCashUser::new("alice", "password").unwrap();
+    let requests = mock.request_count();
+
+    assert!(matches!(
+        methods::get_balance(&session, &alice).await,
+        Err(CCashError::ConnectionNotAvailable)
+    ));
+    assert_eq!(mock.request_count(), requests);
+    assert!(!session.is_connected());
+}
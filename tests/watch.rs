@@ -0,0 +1,149 @@
+use ccash_rs::{
+    mock::{MockFault, MockServer},
+    watch::*,
+    *,
+};
+use futures::StreamExt;
+use std::time::Duration;
+
+const BALANCE: &str = "/v1/user/balance";
+const INTERVAL: Duration = Duration::from_millis(10);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn connect(mock: &MockServer) -> CCashSession {
+    let mut session = CCashSession::new(&mock.url());
+    session.establish_connection().await.unwrap();
+    session
+}
+
+/// Sends each of the `amounts` from alice to bob once the watcher has had time
+/// to take its first poll.
+async fn send_later(session: &CCashSession, amounts: &[u32]) {
+    let alice = CCashUser::new("alice", "password").unwrap();
+
+    tokio::time::sleep(INTERVAL * 5).await;
+    for &amount in amounts {
+        methods::send_funds(session, &alice, "bob", amount)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn yields_new_transactions() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+    let session = connect(&mock).await;
+    let bob = CCashUser::new("bob", "password").unwrap();
+
+    send_later(&session, &[10]).await;
+    let mut stream = Box::pin(watch_transactions(&session, &bob, INTERVAL));
+    let (event, ()) = tokio::join!(
+        tokio::time::timeout(TIMEOUT, stream.next()),
+        send_later(&session, &[20])
+    );
+
+    match event.unwrap().unwrap().unwrap() {
+        TransactionEvent::Transaction(log) => {
+            assert_eq!(log.get_counterparty(), "alice");
+            assert!(log.get_if_receiving());
+            assert_eq!(log.get_amount(), 20);
+        },
+        TransactionEvent::Gap => panic!("unexpected gap"),
+    }
+}
+
+#[tokio::test]
+async fn yields_gap_when_logs_overflow() {
+    let mock = MockServer::start_with(mock::MockConfig {
+        max_log: 2,
+        ..Default::default()
+    })
+    .await;
+    mock.add_user("alice", "password", 100);
+    mock.add_user("bob", "password", 0);
+    let session = connect(&mock).await;
+    let bob = CCashUser::new("bob", "password").unwrap();
+
+    send_later(&session, &[1]).await;
+    let mut stream = Box::pin(watch_transactions(&session, &bob, INTERVAL * 20));
+    let (event, ()) = tokio::join!(
+        tokio::time::timeout(TIMEOUT, stream.next()),
+        send_later(&session, &[2, 3, 4])
+    );
+
+    assert!(matches!(
+        event.unwrap().unwrap().unwrap(),
+        TransactionEvent::Gap
+    ));
+    for amount in 3..=4 {
+        match stream.next().await.unwrap().unwrap() {
+            TransactionEvent::Transaction(log) => assert_eq!(log.get_amount(), amount),
+            TransactionEvent::Gap => panic!("unexpected gap"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn yields_balance_changes() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = connect(&mock).await;
+    let alice = CCashUser::new("alice", "password").unwrap();
+    let mut stream = Box::pin(watch_balance(
+        &session,
+        &alice,
+        BalanceWatchOptions::new(INTERVAL),
+    ));
+
+    match stream.next().await.unwrap().unwrap() {
+        BalanceEvent::Initial { balance, .. } => assert_eq!(balance, 100),
+        event => panic!("unexpected event: {event:?}"),
+    }
+
+    mock.set_balance("alice", 60);
+
+    match stream.next().await.unwrap().unwrap() {
+        BalanceEvent::Changed(change) => {
+            assert_eq!(change.old, 100);
+            assert_eq!(change.new, 60);
+            assert_eq!(change.delta, -40);
+        },
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn yields_lost_and_restored_connections() {
+    let mock = MockServer::start().await;
+    mock.add_user("alice", "password", 100);
+    let session = connect(&mock).await.with_retry_policy(RetryPolicy::none());
+    let alice = CCashUser::new("alice", "password").unwrap();
+    let mut stream = Box::pin(watch_balance(
+        &session,
+        &alice,
+        BalanceWatchOptions::new(INTERVAL),
+    ));
+
+    assert!(matches!(
+        stream.next().await.unwrap().unwrap(),
+        BalanceEvent::Initial { .. }
+    ));
+
+    mock.inject_fault(BALANCE, MockFault::Reject(503));
+    mock.set_balance("alice", 120);
+
+    assert!(matches!(
+        stream.next().await.unwrap().unwrap(),
+        BalanceEvent::ConnectionLost(_)
+    ));
+    assert!(matches!(
+        stream.next().await.unwrap().unwrap(),
+        BalanceEvent::Reconnected
+    ));
+    match stream.next().await.unwrap().unwrap() {
+        BalanceEvent::Changed(change) => assert_eq!(change.delta, 20),
+        event => panic!("unexpected event: {event:?}"),
+    }
+}